@@ -0,0 +1,282 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Component, Path, PathBuf};
+
+use color_eyre::eyre::{bail, WrapErr};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::{debug, trace};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::utils::error::KnownError;
+
+static SAVE_FILE_NAME: &str = "checkpoint.dat";
+static SCE_SYS_PATH: &str = "sce_sys";
+static PARAM_SFO_PATH: &str = "sce_sys/param.sfo";
+static ICON_PATH: &str = "sce_sys/icon0.png";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+  Zip,
+  Tar,
+  TarGz,
+}
+
+impl ArchiveKind {
+  /// Detects the archive kind from the file extension.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+    let name = path.as_ref().file_name()?.to_str()?.to_lowercase();
+
+    if name.ends_with(".zip") {
+      Some(ArchiveKind::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+      Some(ArchiveKind::TarGz)
+    } else if name.ends_with(".tar") {
+      Some(ArchiveKind::Tar)
+    } else {
+      None
+    }
+  }
+}
+
+/// PS4 save read from an archive, with the files needed for the conversion.
+#[derive(Debug)]
+pub struct ArchivedSave {
+  /// Directory of the save inside the archive, empty if the save is in the archive root.
+  pub dir: PathBuf,
+  pub save_data: Vec<u8>,
+  pub param_sfo: Option<Vec<u8>>,
+  pub icon: Option<Vec<u8>>,
+}
+
+impl ArchivedSave {
+  pub fn save_file(&self) -> PathBuf {
+    self.dir.join(SAVE_FILE_NAME)
+  }
+
+  pub fn param_sfo_path(&self) -> PathBuf {
+    self.dir.join(PARAM_SFO_PATH)
+  }
+
+  pub fn sce_sys_path(&self) -> PathBuf {
+    self.dir.join(SCE_SYS_PATH)
+  }
+}
+
+/// Reads the PS4 saves from the archive into memory, every directory with a `checkpoint.dat` file is a save.
+pub fn read_saves<P: AsRef<Path>>(path: P) -> color_eyre::Result<Vec<ArchivedSave>> {
+  let path = path.as_ref();
+  let Some(kind) = ArchiveKind::from_path(path) else {
+    bail!(
+      "unsupported archive {:?}, expected a .zip, .tar or .tar.gz file",
+      path
+    );
+  };
+  let file = BufReader::new(
+    File::open(path).wrap_err_with(|| format!("failed to open archive {:?}", path))?,
+  );
+
+  debug!("Reading {:?} archive {:?}..", kind, path);
+  let mut files = match kind {
+    ArchiveKind::Zip => read_zip(file),
+    ArchiveKind::Tar => read_tar(file),
+    ArchiveKind::TarGz => read_tar(GzDecoder::new(file)),
+  }
+  .wrap_err_with(|| format!("failed to read archive {:?}", path))?;
+  trace!(files = ?files.keys().collect::<Vec<_>>());
+
+  let save_files = files
+    .keys()
+    .filter(|name| name.file_name().is_some_and(|name| name == SAVE_FILE_NAME))
+    .cloned()
+    .collect::<Vec<_>>();
+  // Each file belongs to a single save, so the data is moved out of the map instead of being copied.
+  let saves = save_files
+    .into_iter()
+    .filter_map(|name| {
+      let dir = name.parent().unwrap_or(Path::new("")).to_path_buf();
+
+      Some(ArchivedSave {
+        save_data: files.remove(&name)?,
+        param_sfo: files.remove(&dir.join(PARAM_SFO_PATH)),
+        icon: files.remove(&dir.join(ICON_PATH)),
+        dir,
+      })
+    })
+    .collect::<Vec<_>>();
+
+  if saves.is_empty() {
+    return Err(KnownError::WrongFolder.report(format!(
+      "no {} file found in archive {:?}",
+      SAVE_FILE_NAME, path
+    )));
+  }
+
+  Ok(saves)
+}
+
+/// Checks whether the file from the archive is needed for the conversion.
+fn is_save_file(name: &Path) -> bool {
+  name.file_name().is_some_and(|name| name == SAVE_FILE_NAME)
+    || name.ends_with(PARAM_SFO_PATH)
+    || name.ends_with(ICON_PATH)
+}
+
+fn read_zip(file: BufReader<File>) -> color_eyre::Result<BTreeMap<PathBuf, Vec<u8>>> {
+  let mut archive = ZipArchive::new(file)?;
+  let mut files = BTreeMap::new();
+
+  for index in 0..archive.len() {
+    let mut entry = archive.by_index(index)?;
+    let Some(name) = entry.enclosed_name().map(Path::to_path_buf) else {
+      continue;
+    };
+
+    if entry.is_file() && is_save_file(&name) {
+      let mut data = Vec::with_capacity(entry.size() as usize);
+      entry.read_to_end(&mut data)?;
+      files.insert(name, data);
+    }
+  }
+
+  Ok(files)
+}
+
+fn read_tar<R: Read>(reader: R) -> color_eyre::Result<BTreeMap<PathBuf, Vec<u8>>> {
+  let mut archive = tar::Archive::new(reader);
+  let mut files = BTreeMap::new();
+
+  for entry in archive.entries()? {
+    let mut entry = entry?;
+    let name = entry
+      .path()?
+      .components()
+      .filter(|component| matches!(component, Component::Normal(_)))
+      .collect::<PathBuf>();
+
+    if entry.header().entry_type().is_file() && is_save_file(&name) {
+      let mut data = Vec::with_capacity(entry.size() as usize);
+      entry.read_to_end(&mut data)?;
+      files.insert(name, data);
+    }
+  }
+
+  Ok(files)
+}
+
+/// Collects the files in memory and writes them into the archive once finished.
+#[derive(Debug)]
+pub struct ArchiveWriter {
+  path: PathBuf,
+  kind: ArchiveKind,
+  files: Vec<(String, Vec<u8>)>,
+}
+
+impl ArchiveWriter {
+  pub fn new<P: AsRef<Path>>(path: P) -> color_eyre::Result<Self> {
+    let path = path.as_ref();
+    let Some(kind) = ArchiveKind::from_path(path) else {
+      bail!(
+        "unsupported archive {:?}, expected a .zip, .tar or .tar.gz file",
+        path
+      );
+    };
+
+    Ok(Self {
+      path: path.to_path_buf(),
+      kind,
+      files: vec![],
+    })
+  }
+
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.files.is_empty()
+  }
+
+  /// Adds the file to the archive, returns `false` if a file with the same name was already added.
+  pub fn add(&mut self, name: &str, data: Vec<u8>) -> bool {
+    if self.files.iter().any(|(file_name, _)| file_name == name) {
+      return false;
+    }
+
+    self.files.push((name.to_owned(), data));
+    true
+  }
+
+  pub fn finish(self, overwrite: bool) -> color_eyre::Result<()> {
+    if self.path.exists() && !overwrite {
+      return Err(
+        KnownError::OutputExists.report(format!("archive {:?} already exists", &self.path)),
+      );
+    }
+
+    let file = BufWriter::new(
+      File::create(&self.path)
+        .wrap_err_with(|| format!("failed to create archive {:?}", &self.path))?,
+    );
+    match self.kind {
+      ArchiveKind::Zip => write_zip(file, &self.files),
+      ArchiveKind::Tar => write_tar(file, &self.files).map(|_| ()),
+      ArchiveKind::TarGz => write_tar(GzEncoder::new(file, Compression::default()), &self.files)?
+        .finish()?
+        .flush()
+        .map_err(Into::into),
+    }
+    .wrap_err_with(|| format!("failed to write archive {:?}", &self.path))
+  }
+}
+
+fn write_zip(file: BufWriter<File>, files: &[(String, Vec<u8>)]) -> color_eyre::Result<()> {
+  let mut archive = ZipWriter::new(file);
+
+  for (name, data) in files {
+    archive.start_file(name.as_str(), FileOptions::default())?;
+    archive.write_all(data)?;
+  }
+  archive.finish()?.flush()?;
+
+  Ok(())
+}
+
+fn write_tar<W: Write>(writer: W, files: &[(String, Vec<u8>)]) -> color_eyre::Result<W> {
+  let mut archive = tar::Builder::new(writer);
+
+  for (name, data) in files {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(
+      std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or_default(),
+    );
+    archive.append_data(&mut header, name, data.as_slice())?;
+  }
+
+  Ok(archive.into_inner()?)
+}
@@ -20,7 +20,7 @@ macro_rules! clap_error {
     $crate::cli::macros::clap_error!(kind: clap::error::ErrorKind::ValueValidation, $msg)
   );
   (kind: $kind:expr, $msg:literal $(,)?) => ({
-    let mut cmd = $crate::cli::CliArgs::command();
+    let mut cmd = <$crate::cli::CliArgs as clap::CommandFactory>::command();
       cmd.error(
         $kind,
         core::format_args!($msg)
@@ -30,7 +30,7 @@ macro_rules! clap_error {
     $crate::cli::macros::clap_error!(kind: clap::error::ErrorKind::ValueValidation, $fmt, $($arg)*)
   );
   (kind: $kind:expr, $fmt:expr, $($arg:tt)*) => ({
-      let mut cmd = $crate::cli::CliArgs::command();
+      let mut cmd = <$crate::cli::CliArgs as clap::CommandFactory>::command();
       cmd.error(
         $kind,
         format!($fmt, $($arg)*)
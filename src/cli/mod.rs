@@ -15,34 +15,635 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::io::IsTerminal;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
+use std::time::Duration;
 
-use clap::{Parser, ValueHint};
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{ArgAction, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueHint};
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::cli::macros::clap_error;
+use crate::config::{Config, ConvertConfig};
+use crate::log::{ColorMode, LogFormat, LogOptions};
+use crate::report::ReportFormat;
+use crate::save::{parse_playtime, SaveTitle};
 
 pub mod macros;
 
 #[derive(Parser, Debug)]
+#[command(
+  subcommand_negates_reqs = true,
+  // Passing the save file without a command is an alias for the convert command.
+  override_usage = "hfw-save-converter [OPTIONS] <SAVE_FILE>\n       hfw-save-converter [OPTIONS] <COMMAND>"
+)]
 pub struct CliArgs {
+  /// Always set after [`CliArgs::parse_args`], the save file passed without a command becomes the convert command.
+  #[command(subcommand)]
+  pub command: Option<Command>,
+
+  #[arg(required = true, help = SAVE_FILE_HELP, value_hint = ValueHint::AnyPath)]
+  save_file: Option<PathBuf>,
+
+  #[command(flatten)]
+  convert: ConvertOptions,
+
+  #[arg(
+    long,
+    short = 'v',
+    global = true,
+    group = "log_level",
+    action = ArgAction::Count,
+    help = "Increase logging verbosity of the application, -v enables debug and -vv trace logging"
+  )]
+  pub verbose: u8,
+
+  #[arg(
+    long,
+    global = true,
+    group = "log_level",
+    help = "Enable trace logging for the application, same as -vv"
+  )]
+  pub trace: bool,
+
+  #[arg(
+    long,
+    short = 'q',
+    global = true,
+    group = "log_level",
+    help = "Only log errors"
+  )]
+  pub quiet: bool,
+
+  #[arg(
+    long,
+    global = true,
+    value_name = "DIRECTIVES",
+    help = "Comma separated logging directives applied on top of the log level, e.g. \"sfo=trace\""
+  )]
+  pub log_filter: Option<String>,
+
+  #[arg(
+    long,
+    global = true,
+    value_enum,
+    help = "Format of the emitted logs [default: text]"
+  )]
+  pub log_format: Option<LogFormat>,
+
+  #[arg(
+    long,
+    global = true,
+    value_name = "PATH",
+    value_hint = ValueHint::FilePath,
+    help = "Write a full-detail log to the file, regardless of the console log level"
+  )]
+  pub log_file: Option<PathBuf>,
+
+  #[arg(
+    long,
+    global = true,
+    value_enum,
+    help = "When to colorize the output [default: auto]"
+  )]
+  pub color: Option<ColorMode>,
+
+  #[arg(long, global = true, help = "Do not print the logo on startup")]
+  pub no_logo: bool,
+
+  #[arg(
+    long,
+    global = true,
+    value_name = "PATH",
+    value_hint = ValueHint::FilePath,
+    help = "Path to the config file with the default options, instead of the one in the user's config directory"
+  )]
+  pub config: Option<PathBuf>,
+
+  /// Console log level from the config file, used when no verbosity flag was passed.
+  #[arg(skip)]
+  pub default_log_level: Option<LevelFilter>,
+}
+
+impl CliArgs {
+  /// Parses the arguments, turning the save file passed without a command into the convert command.
+  pub fn parse_args() -> Self {
+    let mut command = Self::command();
+    let matches = command.get_matches_mut();
+    let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    // The top level convert options would be silently ignored by the other commands.
+    if let Some((name, _)) = matches.subcommand() {
+      let options = ConvertOptions::augment_args(clap::Command::new("convert"));
+      let passed = options
+        .get_arguments()
+        .find(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .and_then(|arg| arg.get_long())
+        .map(str::to_owned);
+      if let Some(long) = passed {
+        clap_error!(
+          kind: ErrorKind::ArgumentConflict,
+          "the argument '--{}' can only be used with the convert command, not with '{}'",
+          long,
+          name
+        );
+      }
+    }
+
+    if cli.command.is_none() {
+      cli.command = Some(Command::Convert(ConvertArgs {
+        save_file: cli
+          .save_file
+          .take()
+          .expect("clap requires the save file without a command"),
+        options: std::mem::take(&mut cli.convert),
+      }));
+    }
+
+    cli
+  }
+
+  /// Resolves the log level requested with the verbosity flags, `None` if no flag was passed.
+  pub fn log_level(&self) -> Option<LevelFilter> {
+    if self.quiet {
+      Some(LevelFilter::ERROR)
+    } else if self.trace || self.verbose >= 2 {
+      Some(LevelFilter::TRACE)
+    } else if self.verbose == 1 {
+      Some(LevelFilter::DEBUG)
+    } else {
+      None
+    }
+  }
+
+  pub fn log_format(&self) -> LogFormat {
+    self.log_format.unwrap_or_default()
+  }
+
+  pub fn color(&self) -> ColorMode {
+    self.color.unwrap_or_default()
+  }
+
+  /// Fills the options that weren't passed explicitly with the defaults from the config,
+  /// and updates the config with the explicit flags so it describes the effective options.
+  pub fn merge_config(&mut self, config: &mut Config) {
+    match self.log_level() {
+      Some(level) => config.log.level = Some(level.into()),
+      None => self.default_log_level = config.log.level.map(LevelFilter::from),
+    }
+    config.log.format = *self.log_format.get_or_insert(config.log.format);
+    config.log.color = *self.color.get_or_insert(config.log.color);
+
+    let defaults = &config.convert;
+    match &mut self.command {
+      Some(Command::Convert(args)) => args.options.merge_config(defaults),
+      Some(Command::Watch(args)) => args.merge_config(defaults),
+      Some(Command::Edit(args)) => args.merge_config(defaults),
+      _ => {}
+    }
+  }
+
+  /// Checks whether any of the output was requested in a machine-readable format.
+  pub fn machine_readable(&self) -> bool {
+    self.log_format() == LogFormat::Json
+      || match &self.command {
+        Some(Command::Diff(args)) => args.json,
+        Some(Command::Convert(args)) => {
+          args.options.report.is_some() && args.options.report_file.is_none()
+        }
+        _ => false,
+      }
+  }
+
+  /// Checks whether the logo should be printed, which is only the case for interactive use.
+  pub fn show_logo(&self) -> bool {
+    !self.no_logo && !self.machine_readable() && std::io::stderr().is_terminal()
+  }
+
+  pub fn log_options(&self) -> LogOptions<'_> {
+    LogOptions {
+      level: self.log_level(),
+      default_level: self.default_log_level,
+      filter: self.log_filter.as_deref(),
+      format: self.log_format(),
+      ansi: self.color().enabled(&std::io::stderr()),
+      file: self.log_file.as_deref(),
+    }
+  }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+  #[command(
+    about = "Convert a PS4 save to the PC format, also used when a save file is passed without a command"
+  )]
+  Convert(ConvertArgs),
+  #[command(about = "Show information about a PS4 or PC save file")]
+  Inspect(InspectArgs),
+  #[command(about = "Extract a PC save file into a PS4 save directory")]
+  Extract(ExtractArgs),
+  #[command(about = "Verify the structure and checksum of a PC save file")]
+  Verify(VerifyArgs),
+  #[command(about = "Compare two PS4 or PC save files")]
+  Diff(DiffArgs),
+  #[command(about = "Change the title, subtitle or image of a PC save file")]
+  Edit(EditArgs),
+  #[command(about = "List the save slots in a directory or an archive of PS4 or PC saves")]
+  Slots(SlotsArgs),
+  #[command(about = "Salvage a truncated or damaged PC save file")]
+  Recover(RecoverArgs),
+  #[command(about = "Check that converting a PS4 save to PC and back preserves it")]
+  Roundtrip(RoundtripArgs),
+  #[command(about = "Watch a directory and convert the PS4 saves copied into it")]
+  Watch(WatchArgs),
+  #[command(about = "Manage the config file with the default options")]
+  Config(ConfigArgs),
+}
+
+static SAVE_FILE_HELP: &str = "Path to the decrypted PS4 save file, or a .zip/.tar/.tar.gz archive containing PS4 saves. Use \"-\" to read the save data from the standard input";
+
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+  #[arg(help = SAVE_FILE_HELP, value_hint = ValueHint::AnyPath)]
+  pub save_file: PathBuf,
+
+  #[command(flatten)]
+  pub options: ConvertOptions,
+}
+
+// Conversion options, also used by the watch command to convert the saves it finds.
+#[derive(Args, Debug, Default)]
+pub struct ConvertOptions {
+  #[arg(long = "output", short = 'o', help = "Path to a directory or a .zip/.tar/.tar.gz archive where the generated save files will be stored. Use \"-\" to write the save file to the standard output", value_hint = ValueHint::DirPath)]
+  pub output_dir: Option<PathBuf>,
+
+  #[arg(
+    long,
+    help = "Path to the sce_sys directory containing the param.sfo and icon0.png files, required to keep the metadata when reading the save data from the standard input",
+    value_hint = ValueHint::DirPath
+  )]
+  pub sce_sys: Option<PathBuf>,
+
+  #[arg(long, help = "Overwrite if output file already exists")]
+  pub force: bool,
+
+  #[arg(
+    long,
+    overrides_with = "force",
+    help = "Don't overwrite the output files, even if the config file enables it"
+  )]
+  pub no_force: bool,
+
+  #[arg(
+    long,
+    help = "Skip checking that the save file contains decrypted save data"
+  )]
+  pub skip_payload_check: bool,
+
+  #[arg(
+    long,
+    short = 'j',
+    help = "Number of saves converted in parallel from an archive, defaults to the number of CPUs"
+  )]
+  pub jobs: Option<NonZeroUsize>,
+
+  #[arg(
+    long,
+    help = "Number of previous copies kept when an output file is overwritten, as <name>.1.bak (the newest) to <name>.<N>.bak"
+  )]
+  pub backups: Option<usize>,
+
+  #[arg(
+    long,
+    help = "PS4 account ID whose saves are converted from an archive, matched against the directory names like PS4/SAVEDATA/<account ID>"
+  )]
+  pub account: Option<String>,
+
+  #[command(flatten)]
+  pub image: ImageArgs,
+
+  #[arg(
+    long,
+    overrides_with = "raw_image",
+    help = "Convert the save image to the PC thumbnail format, even if the config file enables --raw-image"
+  )]
+  pub no_raw_image: bool,
+
+  #[command(flatten)]
+  pub title: TitleArgs,
+
+  #[arg(
+    long,
+    value_enum,
+    help = "Print a report describing the conversion in the given format"
+  )]
+  pub report: Option<ReportFormat>,
+
+  #[arg(
+    long,
+    requires = "report",
+    help = "Path to a file where the report will be written instead of the standard output",
+    value_hint = ValueHint::FilePath
+  )]
+  pub report_file: Option<PathBuf>,
+}
+
+impl ConvertOptions {
+  fn merge_config(&mut self, config: &ConvertConfig) {
+    self.output_dir = self.output_dir.take().or(config.output_dir.clone());
+    self.force = !self.no_force && (self.force || config.force);
+    self.image.raw_image = !self.no_raw_image && (self.image.raw_image || config.raw_image);
+    self.jobs = self.jobs.or(config.jobs);
+    self.backups = self.backups.or(config.backups);
+    self.account = self.account.take().or(config.account.clone());
+  }
+}
+
+#[derive(Args, Debug, Default)]
+pub struct ImageArgs {
+  #[arg(
+    long = "image",
+    help = "Path to a PNG, JPEG, WebP or BMP image to use as the save image",
+    value_hint = ValueHint::FilePath
+  )]
+  pub path: Option<PathBuf>,
+
+  #[arg(
+    long,
+    help = "Embed the save image as is, without converting it to the PC thumbnail format"
+  )]
+  pub raw_image: bool,
+}
+
+#[derive(Args, Debug, Default)]
+pub struct TitleArgs {
+  #[arg(
+    long,
+    value_parser = |s: &str| s.parse::<SaveTitle>().map_err(|err| format!("{err:#}")),
+    help = "Override the save title, e.g. \"Reach For The Stars - Level 1 - 00:00:00\""
+  )]
+  pub title: Option<SaveTitle>,
+
+  #[arg(long, help = "Override the quest name in the save title")]
+  pub quest: Option<String>,
+
+  #[arg(long, help = "Override the player level in the save title")]
+  pub level: Option<u32>,
+
+  #[arg(
+    long,
+    value_parser = |s: &str| parse_playtime(s).map_err(|err| format!("{err:#}")),
+    help = "Override the playtime in the save title, in the HH:MM:SS format"
+  )]
+  pub playtime: Option<Duration>,
+
+  #[arg(long, help = "Override the save subtitle, e.g. \"Autosave\"")]
+  pub sub_title: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+  #[arg(help = "Path to the PS4 or PC save file", value_hint = ValueHint::AnyPath)]
+  pub save_file: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct ExtractArgs {
+  #[arg(help = "Path to the PC save file", value_hint = ValueHint::FilePath)]
+  pub save_file: PathBuf,
+
+  #[arg(
+    long = "output",
+    short = 'o',
+    help = "Path to a directory where the PS4 save directory will be created",
+    value_hint = ValueHint::DirPath
+  )]
+  pub output_dir: Option<PathBuf>,
+
+  #[arg(long, help = "Overwrite if output files already exist")]
+  pub force: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct SlotsArgs {
+  #[arg(
+    help = "Path to a directory or a .zip/.tar/.tar.gz archive containing PS4 or PC saves",
+    value_hint = ValueHint::AnyPath
+  )]
+  pub path: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct VerifyArgs {
+  #[arg(help = "Path to the PC save file", value_hint = ValueHint::FilePath)]
+  pub save_file: PathBuf,
+
+  #[arg(
+    long,
+    help = "Path to the PS4 save file the PC save file was converted from",
+    value_hint = ValueHint::AnyPath
+  )]
+  pub source: Option<PathBuf>,
+
+  #[arg(
+    long,
+    requires = "source",
+    help = "Rewrite the checksum if it doesn't match the one from the PS4 save file"
+  )]
+  pub fix_checksum: bool,
+
+  #[arg(long, help = "Write the save file even if its version is unknown")]
+  pub allow_unknown_version: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct DiffArgs {
+  #[arg(help = "Path to the first PS4 or PC save file", value_hint = ValueHint::AnyPath)]
+  pub left: PathBuf,
+
+  #[arg(help = "Path to the second PS4 or PC save file", value_hint = ValueHint::AnyPath)]
+  pub right: PathBuf,
+
+  #[arg(long, help = "Print the differences as JSON")]
+  pub json: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct EditArgs {
+  #[arg(help = "Path to the PC save file", value_hint = ValueHint::FilePath)]
+  pub save_file: PathBuf,
+
+  #[command(flatten)]
+  pub image: ImageArgs,
+
+  #[arg(
+    long,
+    overrides_with = "raw_image",
+    help = "Convert the save image to the PC thumbnail format, even if the config file enables --raw-image"
+  )]
+  pub no_raw_image: bool,
+
+  #[arg(
+    long,
+    conflicts_with = "path",
+    help = "Replace the image of the save file with the placeholder image generated from its title"
+  )]
+  pub strip_image: bool,
+
+  #[command(flatten)]
+  pub title: TitleArgs,
+
+  #[arg(long, help = "Write the save file even if its version is unknown")]
+  pub allow_unknown_version: bool,
+}
+
+impl EditArgs {
+  fn merge_config(&mut self, config: &ConvertConfig) {
+    self.image.raw_image = !self.no_raw_image && (self.image.raw_image || config.raw_image);
+  }
+}
+
+#[derive(Args, Debug)]
+pub struct RecoverArgs {
+  #[arg(help = "Path to the damaged PC save file", value_hint = ValueHint::FilePath)]
+  pub save_file: PathBuf,
+
+  #[arg(
+    long = "output",
+    short = 'o',
+    help = "Path where the rebuilt PC save file will be written",
+    value_hint = ValueHint::FilePath
+  )]
+  pub output: Option<PathBuf>,
+
+  #[arg(
+    long,
+    help = "Path where the usable save data will be written, so it can be converted again",
+    value_hint = ValueHint::FilePath
+  )]
+  pub extract_payload: Option<PathBuf>,
+
+  #[arg(long, help = "Overwrite if output files already exist")]
+  pub force: bool,
+
+  #[arg(long, help = "Write the save file even if its version is unknown")]
+  pub allow_unknown_version: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RoundtripArgs {
   #[arg(help = "Path to the decrypted PS4 save file", value_hint = ValueHint::AnyPath)]
   pub save_file: PathBuf,
 
-  #[arg(long = "output", short = 'o', help = "Path to a directory where the generated save file will be stored", value_hint = ValueHint::DirPath)]
+  #[arg(
+    long,
+    help = "Skip checking that the save file contains decrypted save data"
+  )]
+  pub skip_payload_check: bool,
+
+  #[arg(
+    long,
+    help = "Embed the save image as is, without converting it to the PC thumbnail format"
+  )]
+  pub raw_image: bool,
+
+  #[arg(
+    long,
+    help = "Path to a directory where the intermediate PC and PS4 saves will be kept",
+    value_hint = ValueHint::DirPath
+  )]
+  pub keep: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+  #[arg(help = "Path to the directory where the PS4 saves are copied to", value_hint = ValueHint::DirPath)]
+  pub dir: PathBuf,
+
+  #[arg(
+    long = "output",
+    short = 'o',
+    help = "Path to a directory where the generated save files will be stored",
+    value_hint = ValueHint::DirPath
+  )]
   pub output_dir: Option<PathBuf>,
 
+  #[arg(
+    long,
+    default_value_t = 2,
+    value_parser = clap::value_parser!(u64).range(1..),
+    help = "Seconds between the directory scans, a save is converted once its size didn't change between two scans"
+  )]
+  pub interval: u64,
+
+  #[arg(
+    long,
+    help = "Convert the saves already in the directory and exit instead of watching it"
+  )]
+  pub once: bool,
+
   #[arg(long, help = "Overwrite if output file already exists")]
   pub force: bool,
 
   #[arg(
     long,
-    group = "log_level",
-    help = "Enable verbose logging for the application"
+    overrides_with = "force",
+    help = "Don't overwrite the output files, even if the config file enables it"
   )]
-  pub verbose: bool,
+  pub no_force: bool,
 
   #[arg(
     long,
-    group = "log_level",
-    help = "Enable trace logging for the application"
+    help = "Skip checking that the save file contains decrypted save data"
   )]
-  pub trace: bool,
+  pub skip_payload_check: bool,
+
+  #[arg(
+    long,
+    help = "Embed the save image as is, without converting it to the PC thumbnail format"
+  )]
+  pub raw_image: bool,
+
+  #[arg(
+    long,
+    overrides_with = "raw_image",
+    help = "Convert the save image to the PC thumbnail format, even if the config file enables --raw-image"
+  )]
+  pub no_raw_image: bool,
+
+  #[arg(
+    long,
+    help = "Number of previous copies kept when an output file is overwritten, as <name>.1.bak (the newest) to <name>.<N>.bak"
+  )]
+  pub backups: Option<usize>,
+
+  #[arg(
+    long,
+    help = "PS4 account ID whose saves are converted, matched against the directory names like PS4/SAVEDATA/<account ID>"
+  )]
+  pub account: Option<String>,
+}
+
+impl WatchArgs {
+  fn merge_config(&mut self, config: &ConvertConfig) {
+    self.output_dir = self.output_dir.take().or(config.output_dir.clone());
+    self.force = !self.no_force && (self.force || config.force);
+    self.raw_image = !self.no_raw_image && (self.raw_image || config.raw_image);
+    self.backups = self.backups.or(config.backups);
+    self.account = self.account.take().or(config.account.clone());
+  }
+}
+
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+  #[command(subcommand)]
+  pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+  #[command(about = "Print the effective config, merged from the config file and the CLI flags")]
+  Show,
 }
@@ -0,0 +1,40 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use color_eyre::eyre::WrapErr;
+
+use crate::cli::{ConfigArgs, ConfigCommand};
+use crate::config::{default_path, Config};
+
+pub fn run(args: ConfigArgs, config: &Config) -> color_eyre::Result<()> {
+  match args.command {
+    ConfigCommand::Show => show(config),
+  }
+}
+
+fn show(config: &Config) -> color_eyre::Result<()> {
+  match (&config.path, default_path()) {
+    (Some(path), _) => println!("# Loaded from {:?}", path),
+    (None, Some(path)) => println!("# No config file found at {:?}, using the defaults", path),
+    (None, None) => println!("# No config directory found, using the defaults"),
+  }
+
+  let content = toml::to_string_pretty(config).wrap_err("failed to serialize the config")?;
+  print!("{}", content);
+
+  Ok(())
+}
@@ -0,0 +1,646 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::env;
+use std::io::{IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use path_absolutize::Absolutize;
+use tracing::{debug, debug_span, error, info, trace, warn};
+
+use crate::archive::{read_saves, ArchiveKind, ArchiveWriter, ArchivedSave};
+use crate::cli::macros::clap_error;
+use crate::cli::{ConvertArgs, ConvertOptions};
+use crate::commands::{
+  apply_title_overrides, is_account_save, resolve_metadata, resolve_save_paths, SceSysFiles,
+};
+use crate::log::take_warnings;
+use crate::payload::find_title;
+use crate::progress::Progress;
+use crate::report::{
+  write_reports, ConversionReport, ConversionStatus, ErrorReport, ImageReport, ImageSource,
+  MetadataReport, MetadataSource, OutputReport, ReportField,
+};
+use crate::save::{
+  backup_output, write_output, GenerateOptions, GeneratedSave, SaveFile, SaveMetadata,
+};
+use crate::thumbnail;
+use crate::utils::error::KnownError;
+
+/// The CLI accepts two arguments the "save_file" and the "output_dir"
+/// The first one is a path to the PS4 save file, and the second one
+/// is a path to the location where the generated save file will be stored.
+///
+/// The "save_file" argument is parsed to extract the "base_path" and the actual "save_file" paths,
+/// the first one is the parent directory of the save file,
+/// and the latter is the actual save file path.
+///
+/// Then we proceed to look up the save metadata needed to generate the PC save file.
+/// The easiest way to do this is to look up the `param.sfo` file
+/// either in the `base_path` or in the `base_path/sce_sys` directory.
+///
+/// This file contains PS4 metadata about the save file,
+/// like the name of the file (for example, AUTOSAVE0) or the in-game title.
+/// We can also find the save file image in the `base_path/sce_sys` directory, so we can
+/// assume to search for it under the `base_path` for the maintainability,
+/// or we can abandon the `base_path` search all together.
+///
+/// The latter makes it easier to implement by looking up only one directory.
+/// It also removes the need for the `base_path`, because we can replace it with the `sce_path`
+pub fn run(args: ConvertArgs) -> color_eyre::Result<()> {
+  let ConvertArgs {
+    save_file: input,
+    options: args,
+  } = args;
+  // Only the invalid arguments exit right away, any other failure is recorded in the report.
+  if args.output_dir.as_deref().is_some_and(is_stdio)
+    && args.report.is_some()
+    && args.report_file.is_none()
+  {
+    clap_error!("the report can't be printed when writing the save file to the standard output, use --report-file instead");
+  }
+  if is_stdio(&input) && std::io::stdin().is_terminal() {
+    clap_error!("no save data was piped into the standard input");
+  }
+  if let Some(image_path) = &args.image.path {
+    if !image_path.is_file() {
+      clap_error!("no image found at {:?}", image_path);
+    }
+  }
+
+  let is_archive = !is_stdio(&input) && ArchiveKind::from_path(&input).is_some();
+  let results = match OutputTarget::new(args.output_dir.as_deref(), args.force) {
+    Ok(output) => convert_input(&input, is_archive, &args, output),
+    Err(err) => vec![process(&input, |_| Err(err))],
+  };
+
+  let (reports, mut results): (Vec<_>, Vec<_>) = results.into_iter().unzip();
+  if let Some(format) = args.report {
+    write_reports(&reports, is_archive, format, args.report_file.as_ref())?;
+  }
+
+  if results.len() == 1 {
+    return results.remove(0);
+  }
+
+  let failed = results.iter().filter(|result| result.is_err()).count();
+  for (report, result) in reports.iter().zip(results) {
+    if let Err(err) = result {
+      error!(
+        "Failed to convert {:?}: {:#}",
+        report.save_file.as_ref().unwrap_or(&report.input),
+        err
+      );
+    }
+  }
+  if failed > 0 {
+    bail!("{} of {} saves failed to convert", failed, reports.len());
+  }
+
+  Ok(())
+}
+
+/// Converts the save, the archive or the save data from the standard input into the output,
+/// and finishes the output once all of them were written.
+fn convert_input(
+  input: &Path,
+  is_archive: bool,
+  args: &ConvertOptions,
+  output: OutputTarget,
+) -> Vec<(ConversionReport, color_eyre::Result<()>)> {
+  let output = Mutex::new(output);
+  let mut results = if is_stdio(input) {
+    vec![process(input, |report| {
+      convert_stdin(args, &output, report)
+    })]
+  } else if is_archive {
+    match read_saves(input).and_then(|saves| select_account(input, saves, args)) {
+      Ok(saves) => convert_batch(input, saves, args, &output),
+      Err(err) => vec![process(input, |_| Err(err))],
+    }
+  } else {
+    vec![process(input, |report| {
+      convert(input, args, &output, report)
+    })]
+  };
+
+  if let Err(err) = output.into_inner().unwrap().finish(args) {
+    for (report, result) in &mut results {
+      if matches!(report.status, ConversionStatus::Converted) {
+        report.status = ConversionStatus::Failed;
+        report.error = Some(ErrorReport::from(&err));
+        *result = Err(eyre!("{:#}", err));
+      }
+    }
+  }
+
+  results
+}
+
+/// Keeps only the saves of the account from the arguments, if one was passed.
+fn select_account(
+  archive: &Path,
+  mut saves: Vec<ArchivedSave>,
+  args: &ConvertOptions,
+) -> color_eyre::Result<Vec<ArchivedSave>> {
+  let Some(account) = &args.account else {
+    return Ok(saves);
+  };
+
+  saves.retain(|save| is_account_save(&save.dir, account));
+  if saves.is_empty() {
+    return Err(KnownError::WrongFolder.report(format!(
+      "no save of account {:?} found in archive {:?}",
+      account, archive
+    )));
+  }
+  debug!("Found {} saves of account {:?}", saves.len(), account);
+
+  Ok(saves)
+}
+
+/// Converts a single PS4 save directory into the output directory from the arguments,
+/// for the commands that find the saves on their own.
+pub fn convert_save(
+  input: &Path,
+  args: &ConvertOptions,
+) -> (ConversionReport, color_eyre::Result<()>) {
+  match OutputTarget::new(args.output_dir.as_deref(), args.force) {
+    Ok(output) => process(input, |report| {
+      convert(input, args, &Mutex::new(output), report)
+    }),
+    Err(err) => process(input, |_| Err(err)),
+  }
+}
+
+/// Converts the saves from the archive in parallel, the results are returned in the archive order.
+fn convert_batch(
+  archive: &Path,
+  saves: Vec<ArchivedSave>,
+  args: &ConvertOptions,
+  output: &Mutex<OutputTarget>,
+) -> Vec<(ConversionReport, color_eyre::Result<()>)> {
+  let total = saves.len();
+  let jobs = args
+    .jobs
+    .or_else(|| thread::available_parallelism().ok())
+    .map_or(1, NonZeroUsize::get)
+    .min(total);
+  debug!("Converting {} saves using {} workers..", total, jobs);
+
+  let progress = Progress::new(total);
+  let queue = Mutex::new(saves.into_iter().enumerate());
+  let results = Mutex::new(Vec::with_capacity(total));
+  thread::scope(|scope| {
+    for _ in 0..jobs {
+      scope.spawn(|| loop {
+        let Some((index, save)) = queue.lock().unwrap().next() else {
+          break;
+        };
+
+        let item = save.save_file().to_string_lossy().into_owned();
+        progress.start(&item);
+        let result = process(archive, |report| {
+          convert_archived(archive, save, args, output, report)
+        });
+        progress.inc(&item);
+        results.lock().unwrap().push((index, result));
+      });
+    }
+  });
+  drop(progress);
+
+  let mut results = results.into_inner().unwrap();
+  results.sort_by_key(|(index, _)| *index);
+  results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Runs a single conversion and describes its outcome in the report.
+fn process<F>(input: &Path, convert: F) -> (ConversionReport, color_eyre::Result<()>)
+where
+  F: FnOnce(&mut ConversionReport) -> color_eyre::Result<Option<GeneratedSave>>,
+{
+  let mut report = ConversionReport {
+    input: input.to_path_buf(),
+    ..ConversionReport::default()
+  };
+
+  let result = debug_span!("convert", input = ?input).in_scope(|| convert(&mut report));
+  report.warnings = take_warnings();
+  match &result {
+    Ok(Some(_)) => report.status = ConversionStatus::Converted,
+    Ok(None) => {
+      report.status = ConversionStatus::Skipped;
+      report.error = Some(ErrorReport::new(
+        "output save file already exists".to_owned(),
+        Some(KnownError::OutputExists),
+      ));
+    }
+    Err(err) => {
+      report.status = ConversionStatus::Failed;
+      report.error = Some(ErrorReport::from(err));
+    }
+  }
+
+  (report, result.map(|_| ()))
+}
+
+fn convert(
+  input: &Path,
+  args: &ConvertOptions,
+  output: &Mutex<OutputTarget>,
+  report: &mut ConversionReport,
+) -> color_eyre::Result<Option<GeneratedSave>> {
+  let (sce_sys_path, save_file) = resolve_save_paths(input)?;
+  let sce_sys_path = args.sce_sys.clone().unwrap_or(sce_sys_path);
+  trace!(sce_sys_path = ?&sce_sys_path, save_file = ?&save_file);
+  report.save_file = Some(save_file.clone());
+  let save_dir = sce_sys_path
+    .parent()
+    .ok_or_else(|| eyre!("failed to resolve parent for {:?} path", &sce_sys_path))?
+    .to_path_buf();
+  let sce_sys = SceSysFiles::read(&sce_sys_path)?;
+  let (mut metadata, source, sce_sys_report) = resolve_metadata(&sce_sys, &save_dir)?;
+  report.sce_sys = Some(sce_sys_report);
+
+  let save_data = std::fs::read(&save_file)
+    .wrap_err_with(|| format!("failed to read save data {:?}", &save_file))?;
+  report.metadata = Some(apply_overrides(&mut metadata, source, &save_data, args)?);
+
+  let image = load_image(sce_sys, args)?;
+
+  write_save(&save_file, save_data, image, metadata, args, output, report)
+}
+
+/// Converts the save data read from the standard input, the metadata can only come from the `--sce-sys` directory
+/// and the CLI overrides as there is no save directory to look it up in.
+fn convert_stdin(
+  args: &ConvertOptions,
+  output: &Mutex<OutputTarget>,
+  report: &mut ConversionReport,
+) -> color_eyre::Result<Option<GeneratedSave>> {
+  let stdin = std::io::stdin();
+  info!("Reading save data from the standard input..");
+  let mut save_data = vec![];
+  stdin
+    .lock()
+    .read_to_end(&mut save_data)
+    .wrap_err("failed to read save data from the standard input")?;
+  report.save_file = Some(PathBuf::from("-"));
+
+  let (mut metadata, source, image) = match &args.sce_sys {
+    Some(sce_sys_path) => {
+      let save_dir = sce_sys_path
+        .parent()
+        .ok_or_else(|| eyre!("failed to resolve parent for {:?} path", sce_sys_path))?;
+      let sce_sys = SceSysFiles::read(sce_sys_path)?;
+      let (metadata, source, sce_sys_report) = resolve_metadata(&sce_sys, save_dir)?;
+      report.sce_sys = Some(sce_sys_report);
+      (metadata, source, Some(sce_sys))
+    }
+    None => {
+      warn!("No PS4 metadata directory passed with --sce-sys, default metadata will be used.");
+      (SaveMetadata::default(), MetadataSource::Default, None)
+    }
+  };
+  report.metadata = Some(apply_overrides(&mut metadata, source, &save_data, args)?);
+
+  let image = match image {
+    Some(sce_sys) => load_image(sce_sys, args)?,
+    None => cli_image(args)?,
+  };
+
+  write_save(
+    Path::new("-"),
+    save_data,
+    image,
+    metadata,
+    args,
+    output,
+    report,
+  )
+}
+
+/// Converts the save read from the archive, the files are referred to by their path inside the archive.
+fn convert_archived(
+  archive: &Path,
+  save: ArchivedSave,
+  args: &ConvertOptions,
+  output: &Mutex<OutputTarget>,
+  report: &mut ConversionReport,
+) -> color_eyre::Result<Option<GeneratedSave>> {
+  let save_file = archive.join(save.save_file());
+  info!("Converting {:?} from the archive..", &save_file);
+  report.save_file = Some(save_file.clone());
+
+  let source_file = save.save_file();
+  let sce_sys = SceSysFiles {
+    path: archive.join(save.sce_sys_path()),
+    param_sfo: save.param_sfo,
+    icon: save.icon,
+  };
+  let (mut metadata, source, sce_sys_report) = resolve_metadata(&sce_sys, &save.dir)?;
+  report.sce_sys = Some(sce_sys_report);
+  report.metadata = Some(apply_overrides(
+    &mut metadata,
+    source,
+    &save.save_data,
+    args,
+  )?);
+
+  let image = load_image(sce_sys, args)?;
+
+  write_save(
+    &source_file,
+    save.save_data,
+    image,
+    metadata,
+    args,
+    output,
+    report,
+  )
+}
+
+/// Applies the title found in the save data when there is no param.sfo file and the title overrides from the CLI,
+/// and describes where each of the metadata fields came from.
+fn apply_overrides(
+  metadata: &mut SaveMetadata,
+  source: MetadataSource,
+  save_data: &[u8],
+  args: &ConvertOptions,
+) -> color_eyre::Result<MetadataReport> {
+  // Slot derived metadata only knows the file name and subtitle, the rest comes from the defaults.
+  let fallback_source = match source {
+    MetadataSource::SaveDir => MetadataSource::Default,
+    source => source,
+  };
+  let mut title_source = fallback_source;
+  if source != MetadataSource::Sfo {
+    match find_title(save_data) {
+      Some(title) => {
+        metadata.title = title.format()?;
+        title_source = MetadataSource::Payload;
+        info!(
+          "Found the save title {:?} in the save data",
+          &metadata.title
+        );
+      }
+      None => debug!("No save title found in the save data, the default title will be used"),
+    }
+  }
+
+  (metadata.title, metadata.sub_title) =
+    apply_title_overrides(&metadata.title, &metadata.sub_title, &args.title)?;
+  trace!(metadata = ?&metadata);
+
+  if metadata.sub_title.is_empty() {
+    warn!("The save subtitle is unknown and will be left empty, it can be set with --sub-title.");
+  }
+
+  let title_overridden = args.title.title.is_some()
+    || args.title.quest.is_some()
+    || args.title.level.is_some()
+    || args.title.playtime.is_some();
+
+  Ok(MetadataReport {
+    file_name: ReportField::new(metadata.file_name.clone(), source),
+    title: ReportField::new(
+      metadata.title.clone(),
+      if title_overridden {
+        MetadataSource::Cli
+      } else {
+        title_source
+      },
+    ),
+    sub_title: ReportField::new(
+      metadata.sub_title.clone(),
+      if args.title.sub_title.is_some() {
+        MetadataSource::Cli
+      } else {
+        source
+      },
+    ),
+    checksum: ReportField::new(metadata.checksum, fallback_source),
+  })
+}
+
+/// Loads the image passed through the CLI, which takes precedence over the PS4 save image.
+fn cli_image(args: &ConvertOptions) -> color_eyre::Result<Option<(Vec<u8>, ImageReport)>> {
+  let Some(image_path) = &args.image.path else {
+    return Ok(None);
+  };
+
+  Ok(Some((
+    thumbnail::load(image_path, args.image.raw_image)?,
+    ImageReport {
+      source: ImageSource::Cli,
+      path: Some(image_path.clone()),
+      raw: args.image.raw_image,
+    },
+  )))
+}
+
+/// Loads the save image from the CLI or the PS4 metadata directory, if there is one.
+fn load_image(
+  sce_sys: SceSysFiles,
+  args: &ConvertOptions,
+) -> color_eyre::Result<Option<(Vec<u8>, ImageReport)>> {
+  if let Some(image) = cli_image(args)? {
+    return Ok(Some(image));
+  }
+
+  let icon_path = sce_sys.icon_path();
+  sce_sys
+    .icon
+    .map(|icon| {
+      let _span =
+        debug_span!("load_image", path = ?&icon_path, raw = args.image.raw_image).entered();
+      let image = thumbnail::prepare(icon, args.image.raw_image)
+        .wrap_err_with(|| format!("failed to process save image {:?}", &icon_path))?;
+
+      Ok((
+        image,
+        ImageReport {
+          source: ImageSource::Sfo,
+          path: Some(icon_path),
+          raw: args.image.raw_image,
+        },
+      ))
+    })
+    .transpose()
+}
+
+/// Builds the PC save file and writes it to the output, `None` is returned if it was skipped.
+fn write_save(
+  source: &Path,
+  save_data: Vec<u8>,
+  image: Option<(Vec<u8>, ImageReport)>,
+  metadata: SaveMetadata,
+  args: &ConvertOptions,
+  output: &Mutex<OutputTarget>,
+  report: &mut ConversionReport,
+) -> color_eyre::Result<Option<GeneratedSave>> {
+  let (image, image_report) = image.unzip();
+  let (save, placeholder_image) = SaveFile::build(
+    source,
+    save_data,
+    image,
+    metadata,
+    &GenerateOptions {
+      skip_payload_check: args.skip_payload_check,
+    },
+  )?;
+
+  let _span = debug_span!("write_save", path = ?save.path()).entered();
+  let bytes = save.to_bytes()?;
+  let Some(path) = output.lock().unwrap().write(save.path(), &bytes, args)? else {
+    return Ok(None);
+  };
+  if is_stdio(&path) {
+    info!("Wrote the generated save file to the standard output");
+  } else {
+    info!("Saved the generated save file to {:?}", &path);
+  }
+  let generated = GeneratedSave::new(path, &bytes, &save, placeholder_image);
+
+  report.image = Some(match image_report {
+    Some(image_report) if !generated.placeholder_image => image_report,
+    _ => ImageReport {
+      source: ImageSource::Placeholder,
+      path: None,
+      raw: false,
+    },
+  });
+  report.output = Some(OutputReport {
+    path: generated.path.clone(),
+    bytes_written: generated.size,
+    payload_sha256: generated.payload_sha256.clone(),
+    output_sha256: generated.sha256.clone(),
+  });
+
+  Ok(Some(generated))
+}
+
+/// Where the generated save files are written, either a directory, an archive or the standard output.
+enum OutputTarget {
+  Dir(PathBuf),
+  Archive(ArchiveWriter),
+  /// Tracks whether a save file was already written, as only one fits into the standard output.
+  Stdout(bool),
+}
+
+/// Checks whether the path is `-`, referring to the standard input or output.
+fn is_stdio(path: &Path) -> bool {
+  path == Path::new("-")
+}
+
+impl OutputTarget {
+  fn new(output: Option<&Path>, overwrite: bool) -> color_eyre::Result<Self> {
+    let output = match output {
+      Some(output) if is_stdio(output) => return Ok(OutputTarget::Stdout(false)),
+      Some(output) => output
+        .absolutize()
+        .wrap_err("failed to resolve output path")?
+        .to_path_buf(),
+      None => env::current_dir().wrap_err("failed to resolve current working directory")?,
+    };
+
+    if ArchiveKind::from_path(&output).is_none() {
+      return Ok(OutputTarget::Dir(output));
+    }
+
+    if output.exists() && !overwrite {
+      return Err(KnownError::OutputExists.report(format!("archive {:?} already exists", &output)));
+    }
+
+    Ok(OutputTarget::Archive(ArchiveWriter::new(output)?))
+  }
+
+  /// Writes the save file, `None` is returned if it was skipped because it already exists.
+  fn write(
+    &mut self,
+    file_name: &Path,
+    data: &[u8],
+    args: &ConvertOptions,
+  ) -> color_eyre::Result<Option<PathBuf>> {
+    match self {
+      OutputTarget::Dir(dir) => {
+        if !dir.exists() {
+          std::fs::create_dir_all(&dir)
+            .wrap_err_with(|| format!("failed to create output directory {:?}", &dir))?;
+        } else if !dir.is_dir() {
+          bail!("output path is not a directory");
+        }
+
+        let path = dir.join(file_name);
+        if args.force {
+          backup_output(&path, args.backups.unwrap_or_default())?;
+        }
+        Ok(write_output(&path, data, args.force)?.then_some(path))
+      }
+      OutputTarget::Archive(archive) => {
+        let name = file_name.to_string_lossy();
+        if !archive.add(&name, data.to_vec()) {
+          error!(
+            "Unable to add the save file to the archive, {:?} was already added.",
+            name
+          );
+          return Ok(None);
+        }
+
+        Ok(Some(archive.path().join(file_name)))
+      }
+      OutputTarget::Stdout(written) => {
+        if *written {
+          bail!("only a single save file can be written to the standard output");
+        }
+
+        let mut stdout = std::io::stdout().lock();
+        stdout
+          .write_all(data)
+          .and_then(|_| stdout.flush())
+          .wrap_err("failed to write the save file to the standard output")?;
+        *written = true;
+
+        Ok(Some(PathBuf::from("-")))
+      }
+    }
+  }
+
+  fn finish(self, args: &ConvertOptions) -> color_eyre::Result<()> {
+    match self {
+      OutputTarget::Dir(_) | OutputTarget::Stdout(_) => Ok(()),
+      OutputTarget::Archive(archive) if archive.is_empty() => {
+        warn!("No save file was converted, skipping the output archive.");
+        Ok(())
+      }
+      OutputTarget::Archive(archive) => {
+        let path = archive.path().to_path_buf();
+        if args.force {
+          backup_output(&path, args.backups.unwrap_or_default())?;
+        }
+        archive.finish(args.force)?;
+        info!("Saved the generated save files to archive {:?}", &path);
+        Ok(())
+      }
+    }
+  }
+}
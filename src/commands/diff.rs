@@ -0,0 +1,486 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::WrapErr;
+use serde::Serialize;
+use tracing::{debug, trace, warn};
+
+use crate::cli::DiffArgs;
+use crate::commands::resolve_save_paths;
+use crate::save::{SaveFile, SaveMetadata, GGDS_MAGIC};
+use crate::sfo::SFOFile;
+use crate::utils;
+
+static RANGES_DISPLAY_LIMIT: usize = 16;
+
+pub fn run(args: DiffArgs) -> color_eyre::Result<()> {
+  let left = LoadedSave::load(&args.left)?;
+  let right = LoadedSave::load(&args.right)?;
+
+  debug!("Comparing save files..");
+  let diff = SaveDiff::new(&left, &right);
+
+  if args.json {
+    println!(
+      "{}",
+      serde_json::to_string_pretty(&diff).wrap_err("failed to serialize the diff")?
+    );
+  } else {
+    diff.print();
+  }
+
+  Ok(())
+}
+
+struct LoadedSave {
+  kind: &'static str,
+  path: PathBuf,
+  header: BTreeMap<&'static str, String>,
+  params: BTreeMap<String, String>,
+  image: Option<Vec<u8>>,
+  data: Vec<u8>,
+}
+
+impl LoadedSave {
+  fn load(path: &Path) -> color_eyre::Result<Self> {
+    let (sce_sys_path, save_file) = resolve_save_paths(path)?;
+    trace!(sce_sys_path = ?&sce_sys_path, save_file = ?&save_file);
+
+    let data = fs::read(&save_file)
+      .wrap_err_with(|| format!("failed to read save file {:?}", &save_file))?;
+
+    if data.starts_with(GGDS_MAGIC) {
+      debug!("Parsing PC save file {:?}..", &save_file);
+      let save = SaveFile::open(&save_file).wrap_err("failed to read PC save file")?;
+
+      return Ok(Self {
+        kind: "PC",
+        path: save_file,
+        header: BTreeMap::from([
+          ("version", save.version.to_string()),
+          ("checksum", save.checksum.to_string()),
+          ("title", save.title.to_string()),
+          ("sub_title", save.sub_title.to_string()),
+          ("trailing_length", save.trailing.len().to_string()),
+        ]),
+        params: BTreeMap::new(),
+        image: Some(save.image),
+        data: save.data,
+      });
+    }
+
+    let sfo_path = sce_sys_path.join("param.sfo");
+    let icon_path = sce_sys_path.join("icon0.png");
+
+    let (header, params) = if sfo_path.is_file() {
+      debug!("Parsing param.sfo file {:?}..", &sfo_path);
+      let sfo_file = SFOFile::open(&sfo_path).wrap_err("failed to read param.sfo file")?;
+      let params = sfo_file
+        .params
+        .iter()
+        .map(|(key, value)| (key.clone(), value.to_string()))
+        .collect();
+
+      let header = match TryInto::<SaveMetadata>::try_into(sfo_file) {
+        Ok(metadata) => BTreeMap::from([
+          ("checksum", metadata.checksum.to_string()),
+          ("title", metadata.title),
+          ("sub_title", metadata.sub_title),
+        ]),
+        Err(err) => {
+          warn!(
+            "Unable to resolve save metadata from {:?}: {:#}",
+            &sfo_path, err
+          );
+          BTreeMap::new()
+        }
+      };
+
+      (header, params)
+    } else {
+      (BTreeMap::new(), BTreeMap::new())
+    };
+
+    let image = if icon_path.is_file() {
+      Some(
+        fs::read(&icon_path)
+          .wrap_err_with(|| format!("failed to read save image {:?}", &icon_path))?,
+      )
+    } else {
+      None
+    };
+
+    Ok(Self {
+      kind: "PS4",
+      path: save_file,
+      header,
+      params,
+      image,
+      data,
+    })
+  }
+}
+
+#[derive(Serialize)]
+struct SaveDiff {
+  left: SaveSummary,
+  right: SaveSummary,
+  header: Vec<FieldDiff>,
+  params: Vec<FieldDiff>,
+  image: ImageDiff,
+  payload: PayloadDiff,
+}
+
+#[derive(Serialize)]
+struct SaveSummary {
+  kind: &'static str,
+  path: PathBuf,
+}
+
+#[derive(Serialize)]
+struct FieldDiff {
+  field: String,
+  left: Option<String>,
+  right: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ImageDiff {
+  identical: bool,
+  left: Option<ImageSummary>,
+  right: Option<ImageSummary>,
+}
+
+#[derive(Serialize)]
+struct ImageSummary {
+  width: Option<u32>,
+  height: Option<u32>,
+  size: usize,
+  sha256: String,
+}
+
+#[derive(Serialize)]
+struct PayloadDiff {
+  identical: bool,
+  left: PayloadSummary,
+  right: PayloadSummary,
+  ranges: Vec<Range<usize>>,
+}
+
+#[derive(Serialize)]
+struct PayloadSummary {
+  size: usize,
+  sha256: String,
+}
+
+impl SaveDiff {
+  fn new(left: &LoadedSave, right: &LoadedSave) -> Self {
+    let header = diff_fields(
+      left
+        .header
+        .iter()
+        .map(|(key, value)| (key.to_string(), value)),
+      right
+        .header
+        .iter()
+        .map(|(key, value)| (key.to_string(), value)),
+    );
+    let params = diff_fields(
+      left.params.iter().map(|(key, value)| (key.clone(), value)),
+      right.params.iter().map(|(key, value)| (key.clone(), value)),
+    );
+
+    Self {
+      left: SaveSummary {
+        kind: left.kind,
+        path: left.path.clone(),
+      },
+      right: SaveSummary {
+        kind: right.kind,
+        path: right.path.clone(),
+      },
+      header,
+      params,
+      image: ImageDiff {
+        identical: left.image == right.image,
+        left: left.image.as_deref().map(ImageSummary::new),
+        right: right.image.as_deref().map(ImageSummary::new),
+      },
+      payload: PayloadDiff {
+        identical: left.data == right.data,
+        left: PayloadSummary::new(&left.data),
+        right: PayloadSummary::new(&right.data),
+        ranges: diff_ranges(&left.data, &right.data),
+      },
+    }
+  }
+
+  fn print(&self) {
+    println!(
+      "Comparing {} save file {:?} with {} save file {:?}",
+      self.left.kind, self.left.path, self.right.kind, self.right.path
+    );
+
+    println!("Header");
+    print_fields(&self.header);
+
+    println!("SFO params");
+    print_fields(&self.params);
+
+    println!("Image");
+    if self.image.identical {
+      println!("  identical");
+    } else {
+      for (side, image) in [("left", &self.image.left), ("right", &self.image.right)] {
+        match image {
+          Some(image) => println!("  {}: {}", side, image),
+          None => println!("  {}: <missing>", side),
+        }
+      }
+    }
+
+    println!("Save data");
+    if self.payload.identical {
+      println!("  identical");
+    } else {
+      println!("  left: {}", self.payload.left);
+      println!("  right: {}", self.payload.right);
+      println!("  differing ranges: {}", self.payload.ranges.len());
+      for range in self.payload.ranges.iter().take(RANGES_DISPLAY_LIMIT) {
+        println!(
+          "    {:#010x}..{:#010x} ({} bytes)",
+          range.start,
+          range.end,
+          range.len()
+        );
+      }
+      if self.payload.ranges.len() > RANGES_DISPLAY_LIMIT {
+        println!(
+          "    ... and {} more",
+          self.payload.ranges.len() - RANGES_DISPLAY_LIMIT
+        );
+      }
+    }
+  }
+}
+
+impl ImageSummary {
+  fn new(image: &[u8]) -> Self {
+    let dimensions = utils::image_dimensions(image).ok();
+
+    Self {
+      width: dimensions.map(|(width, _)| width),
+      height: dimensions.map(|(_, height)| height),
+      size: image.len(),
+      sha256: utils::sha256_hex(image),
+    }
+  }
+}
+
+impl std::fmt::Display for ImageSummary {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match (self.width, self.height) {
+      (Some(width), Some(height)) => write!(fmt, "{}x{}, ", width, height)?,
+      _ => write!(fmt, "<invalid image>, ")?,
+    }
+
+    write!(fmt, "{} bytes, sha256 {}", self.size, self.sha256)
+  }
+}
+
+impl PayloadSummary {
+  fn new(data: &[u8]) -> Self {
+    Self {
+      size: data.len(),
+      sha256: utils::sha256_hex(data),
+    }
+  }
+}
+
+impl std::fmt::Display for PayloadSummary {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(fmt, "{} bytes, sha256 {}", self.size, self.sha256)
+  }
+}
+
+fn diff_fields<'a, L, R>(left: L, right: R) -> Vec<FieldDiff>
+where
+  L: Iterator<Item = (String, &'a String)>,
+  R: Iterator<Item = (String, &'a String)>,
+{
+  let left = left.collect::<BTreeMap<_, _>>();
+  let right = right.collect::<BTreeMap<_, _>>();
+
+  left
+    .keys()
+    .chain(right.keys())
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .filter(|key| left.get(*key) != right.get(*key))
+    .map(|key| FieldDiff {
+      field: key.clone(),
+      left: left.get(key).map(|value| value.to_string()),
+      right: right.get(key).map(|value| value.to_string()),
+    })
+    .collect()
+}
+
+fn print_fields(fields: &[FieldDiff]) {
+  if fields.is_empty() {
+    println!("  identical");
+  }
+
+  for field in fields {
+    println!(
+      "  {}: {} != {}",
+      field.field,
+      field.left.as_deref().unwrap_or("<missing>"),
+      field.right.as_deref().unwrap_or("<missing>")
+    );
+  }
+}
+
+/// Collects the ranges of bytes that differ between the two payloads,
+/// bytes past the end of the shorter payload are reported as a single range.
+fn diff_ranges(left: &[u8], right: &[u8]) -> Vec<Range<usize>> {
+  let mut ranges: Vec<Range<usize>> = vec![];
+
+  for (offset, _) in left
+    .iter()
+    .zip(right)
+    .enumerate()
+    .filter(|(_, (left, right))| left != right)
+  {
+    match ranges.last_mut() {
+      Some(range) if range.end == offset => range.end += 1,
+      _ => ranges.push(offset..offset + 1),
+    }
+  }
+
+  let (min, max) = (left.len().min(right.len()), left.len().max(right.len()));
+  if min != max {
+    match ranges.last_mut() {
+      Some(range) if range.end == min => range.end = max,
+      _ => ranges.push(min..max),
+    }
+  }
+
+  ranges
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pc_save(title: &str, checksum: u32, data: &[u8]) -> LoadedSave {
+    LoadedSave {
+      kind: "PC",
+      path: PathBuf::from("autosave0.dat"),
+      header: BTreeMap::from([
+        ("version", "1".to_owned()),
+        ("checksum", checksum.to_string()),
+        ("title", title.to_owned()),
+        ("sub_title", "Autosave".to_owned()),
+      ]),
+      params: BTreeMap::new(),
+      image: Some(b"image".to_vec()),
+      data: data.to_vec(),
+    }
+  }
+
+  #[test]
+  fn identical_saves_have_no_differences() {
+    let save = pc_save("Title", 1, b"save data");
+    let diff = SaveDiff::new(&save, &save);
+
+    assert!(diff.header.is_empty());
+    assert!(diff.params.is_empty());
+    assert!(diff.image.identical);
+    assert!(diff.payload.identical);
+    assert!(diff.payload.ranges.is_empty());
+  }
+
+  #[test]
+  fn adjacent_differing_bytes_are_merged() {
+    assert_eq!(
+      diff_ranges(b"abcdefgh", b"aXXdeYgZ"),
+      vec![1..3, 5..6, 7..8]
+    );
+  }
+
+  #[test]
+  fn different_lengths_report_the_extra_bytes() {
+    assert_eq!(diff_ranges(b"abcd", b"abcdef"), vec![4..6]);
+    assert_eq!(diff_ranges(b"abcdef", b"abcd"), vec![4..6]);
+    // The extra bytes continue the differing range at the end of the shorter payload.
+    assert_eq!(diff_ranges(b"abcd", b"abXYef"), vec![2..6]);
+    assert_eq!(diff_ranges(b"", b"ab"), vec![0..2]);
+  }
+
+  #[test]
+  fn only_the_differing_fields_are_reported() {
+    let left = pc_save("Title - Level 1 - 00:00:01", 1, b"save data");
+    let right = pc_save("Title - Level 2 - 00:00:02", 2, b"save data");
+    let diff = SaveDiff::new(&left, &right);
+
+    let fields = diff
+      .header
+      .iter()
+      .map(|field| {
+        (
+          field.field.as_str(),
+          field.left.as_deref(),
+          field.right.as_deref(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      fields,
+      vec![
+        ("checksum", Some("1"), Some("2")),
+        (
+          "title",
+          Some("Title - Level 1 - 00:00:01"),
+          Some("Title - Level 2 - 00:00:02")
+        ),
+      ]
+    );
+    assert!(diff.payload.identical);
+    assert!(diff.image.identical);
+  }
+
+  #[test]
+  fn missing_fields_are_reported() {
+    let left = [("CATEGORY".to_owned(), "sd".to_owned())];
+    let right = [("TITLE_ID".to_owned(), "CUSA24767".to_owned())];
+    let diff = diff_fields(
+      left.iter().map(|(key, value)| (key.clone(), value)),
+      right.iter().map(|(key, value)| (key.clone(), value)),
+    );
+
+    assert_eq!(diff.len(), 2);
+    assert_eq!(diff[0].field, "CATEGORY");
+    assert_eq!(diff[0].right, None);
+    assert_eq!(diff[1].field, "TITLE_ID");
+    assert_eq!(diff[1].left, None);
+  }
+}
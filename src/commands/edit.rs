@@ -0,0 +1,62 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use color_eyre::eyre::{bail, WrapErr};
+use tracing::{debug, info, trace};
+
+use crate::cli::EditArgs;
+use crate::commands::apply_title_overrides;
+use crate::save::SaveFile;
+use crate::thumbnail;
+
+pub fn run(args: EditArgs) -> color_eyre::Result<()> {
+  debug!("Parsing PC save file..");
+  let mut save = SaveFile::open(&args.save_file).wrap_err("failed to read PC save file")?;
+  trace!(save = ?&save);
+
+  let (title, sub_title) = apply_title_overrides(
+    &save.title.to_string(),
+    &save.sub_title.to_string(),
+    &args.title,
+  )?;
+  let mut changed = title != save.title.to_string() || sub_title != save.sub_title.to_string();
+  save.title = title.into();
+  save.sub_title = sub_title.into();
+
+  if let Some(image_path) = &args.image.path {
+    save.image = thumbnail::load(image_path, args.image.raw_image)?;
+    changed = true;
+  } else if args.strip_image {
+    // An empty image isn't known to load in the game, so the image is replaced with the placeholder instead.
+    info!("Replacing the save image with the placeholder image..");
+    save.image =
+      thumbnail::generate_placeholder_image(&save.title.to_string(), &save.sub_title.to_string())?;
+    changed = true;
+  }
+
+  if !changed {
+    bail!(
+      "nothing to change, use --title, --sub-title, --image or --strip-image to edit the save file"
+    );
+  }
+
+  trace!(save = ?&save);
+  save.write_to(save.path(), args.allow_unknown_version)?;
+  info!("Saved the edited save file to {:?}", save.path());
+
+  Ok(())
+}
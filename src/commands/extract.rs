@@ -0,0 +1,44 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::env;
+
+use color_eyre::eyre::WrapErr;
+use tracing::{debug, info, trace};
+
+use crate::cli::ExtractArgs;
+use crate::save::SaveFile;
+
+pub fn run(args: ExtractArgs) -> color_eyre::Result<()> {
+  debug!("Parsing PC save file..");
+  let save = SaveFile::open(&args.save_file).wrap_err("failed to read PC save file")?;
+  trace!(save = ?&save);
+
+  let output_dir = match args.output_dir {
+    Some(output_dir) => output_dir,
+    None => env::current_dir().wrap_err("failed to resolve current working directory")?,
+  };
+
+  let exported = save.export_ps4(&output_dir, args.force)?;
+  trace!(exported = ?&exported);
+  info!(
+    "Extracted the PS4 save to {:?}",
+    exported.save_file.parent().unwrap_or(&output_dir)
+  );
+
+  Ok(())
+}
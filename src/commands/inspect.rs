@@ -0,0 +1,129 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::WrapErr;
+use tracing::{debug, trace};
+
+use crate::cli::InspectArgs;
+use crate::commands::resolve_save_paths;
+use crate::payload::{find_title, validate_payload, PayloadInfo};
+use crate::save::{SaveFile, GGDS_MAGIC};
+use crate::sfo::SFOFile;
+use crate::utils;
+
+pub fn run(args: InspectArgs) -> color_eyre::Result<()> {
+  let (sce_sys_path, save_file) = resolve_save_paths(&args.save_file)?;
+  trace!(sce_sys_path = ?&sce_sys_path, save_file = ?&save_file);
+
+  let data =
+    fs::read(&save_file).wrap_err_with(|| format!("failed to read save file {:?}", &save_file))?;
+
+  if data.starts_with(GGDS_MAGIC) {
+    debug!("Parsing PC save file..");
+    let save = SaveFile::open(&save_file).wrap_err("failed to read PC save file")?;
+    trace!(save = ?&save);
+
+    println!("PC save file {:?}", &save_file);
+    println!("  Version:     {}", save.version);
+    println!("  Checksum:    {0} ({0:#010x})", save.checksum);
+    println!("  Title:       {:?}", save.title.to_string());
+    println!("  Subtitle:    {:?}", save.sub_title.to_string());
+    print_image(&save.image);
+    if !save.trailing.is_empty() {
+      println!("  Trailing:    {} bytes", save.trailing.len());
+    }
+    print_payload(&save.data);
+  } else {
+    println!("PS4 save file {:?}", &save_file);
+    print_sce_sys(&sce_sys_path)?;
+    print_payload(&data);
+  }
+
+  Ok(())
+}
+
+fn print_sce_sys(sce_sys_path: &Path) -> color_eyre::Result<()> {
+  let sfo_path = sce_sys_path.join("param.sfo");
+  let icon_path = sce_sys_path.join("icon0.png");
+
+  if sfo_path.is_file() {
+    debug!("Parsing param.sfo file..");
+    let sfo_file = SFOFile::open(&sfo_path).wrap_err("failed to read param.sfo file")?;
+    trace!(sfo_file = ?&sfo_file);
+
+    let mut params = sfo_file.params.iter().collect::<Vec<_>>();
+    params.sort_by_key(|(key, _)| *key);
+
+    println!("  SFO version: {:#x}", sfo_file.header.version);
+    for (key, value) in params {
+      println!("    {}: {}", key, value);
+    }
+  } else {
+    println!("  SFO version: <missing param.sfo>");
+  }
+
+  if icon_path.is_file() {
+    let image = fs::read(&icon_path)
+      .wrap_err_with(|| format!("failed to read save image {:?}", &icon_path))?;
+    print_image(&image);
+  } else {
+    println!("  Image:       <missing icon0.png>");
+  }
+
+  Ok(())
+}
+
+fn print_image(image: &[u8]) {
+  if image.is_empty() {
+    println!("  Image:       <none>");
+    return;
+  }
+
+  match utils::image_dimensions(image) {
+    Ok((width, height)) => println!(
+      "  Image:       {}x{} ({} bytes)",
+      width,
+      height,
+      image.len()
+    ),
+    Err(err) => println!("  Image:       <{:#}> ({} bytes)", err, image.len()),
+  }
+}
+
+fn print_payload(data: &[u8]) {
+  let info = PayloadInfo::new(data);
+
+  println!("Save data");
+  println!("  Size:        {} bytes", info.size);
+  println!(
+    "  Entropy:     {:.3} bits per byte ({} of {} blocks look random)",
+    info.entropy.average, info.entropy.high_entropy_blocks, info.entropy.blocks
+  );
+  match find_title(data) {
+    Some(title) => println!("  Title:       {:?}", title.to_string()),
+    None => println!("  Title:       <not found>"),
+  }
+  match validate_payload(data) {
+    Ok(_) => println!("  Fits GGDS:   yes"),
+    Err(err) => println!("  Fits GGDS:   no, {:#}", err),
+  }
+  // The decrypted checkpoint format is not documented, so there's nothing more we can check.
+  println!("  Structure:   not validated, the checkpoint data format is unknown");
+}
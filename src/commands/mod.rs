@@ -0,0 +1,230 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+
+use color_eyre::eyre::{eyre, WrapErr};
+use path_absolutize::Absolutize;
+use tracing::{debug, info, instrument, trace, warn};
+
+use crate::cli::TitleArgs;
+use crate::report::{MetadataSource, SceSysReport};
+use crate::save::{SaveMetadata, SaveTitle};
+use crate::sfo::SFOFile;
+use crate::utils::error::KnownError;
+
+pub mod config;
+pub mod convert;
+pub mod diff;
+pub mod edit;
+pub mod extract;
+pub mod inspect;
+pub mod recover;
+pub mod roundtrip;
+pub mod slots;
+pub mod verify;
+pub mod watch;
+
+/// Resolves the `sce_sys` directory and the save file path from the path passed to the CLI,
+/// which can point either to the save file itself or to the directory containing it.
+#[instrument(name = "resolve_paths", level = "debug", skip_all, fields(path = ?path.as_ref()))]
+pub fn resolve_save_paths<P: AsRef<Path>>(path: P) -> color_eyre::Result<(PathBuf, PathBuf)> {
+  debug!("Resolving save file location..");
+  let path = &path
+    .as_ref()
+    .absolutize()
+    .wrap_err("failed to resolve save file path")?;
+
+  if !path.exists() {
+    return Err(KnownError::WrongFolder.report(format!("path {:?} doesn't exist", &path)));
+  }
+
+  let verify_save_file =
+    |base_path: PathBuf, save_file: PathBuf| -> color_eyre::Result<(PathBuf, PathBuf)> {
+      if !save_file.exists() || !save_file.is_file() {
+        return Err(
+          KnownError::WrongFolder.report(format!("no save file found in {:?}", &base_path)),
+        );
+      }
+
+      File::open(&save_file).wrap_err_with(|| format!("cannot open file {:?}", &save_file))?;
+
+      Ok((base_path, save_file))
+    };
+
+  let (base_path, save_file) = if path.is_dir() {
+    let base_path = path.to_path_buf();
+    let save_file = path.join("checkpoint.dat");
+
+    verify_save_file(base_path, save_file)?
+  } else {
+    let base_path = path
+      .parent()
+      .ok_or_else(|| eyre!("failed to resolve parent for {:?} path", &path))?
+      .to_path_buf();
+    let save_file = path.to_path_buf();
+
+    verify_save_file(base_path, save_file)?
+  };
+
+  Ok((base_path.join("sce_sys"), save_file))
+}
+
+/// Applies the title overrides passed through the CLI, returning the new title and subtitle.
+/// The `--quest`, `--level` and `--playtime` ones are applied on top of the current (or overridden) title.
+pub fn apply_title_overrides(
+  title: &str,
+  sub_title: &str,
+  args: &TitleArgs,
+) -> color_eyre::Result<(String, String)> {
+  let sub_title = args
+    .sub_title
+    .clone()
+    .unwrap_or_else(|| sub_title.to_owned());
+  let mut parsed_title = match &args.title {
+    Some(title) => title.clone(),
+    None if args.quest.is_some() || args.level.is_some() || args.playtime.is_some() => title
+      .parse::<SaveTitle>()
+      .wrap_err("failed to parse the save title, use --title to override it as a whole")?,
+    None => return Ok((title.to_owned(), sub_title)),
+  };
+
+  if let Some(quest) = &args.quest {
+    parsed_title.quest = quest.clone();
+  }
+  if let Some(level) = args.level {
+    parsed_title.level = level;
+  }
+  if let Some(playtime) = args.playtime {
+    parsed_title.playtime = playtime;
+  }
+
+  Ok((parsed_title.format()?, sub_title))
+}
+
+static PARAM_SFO_NAME: &str = "param.sfo";
+static ICON_NAME: &str = "icon0.png";
+
+/// Files of the PS4 `sce_sys` directory, read from the filesystem or from an archive.
+pub struct SceSysFiles {
+  /// Path of the directory, only used to refer to the files.
+  pub path: PathBuf,
+  pub param_sfo: Option<Vec<u8>>,
+  pub icon: Option<Vec<u8>>,
+}
+
+impl SceSysFiles {
+  /// Reads the files of the `sce_sys` directory, the ones that don't exist are left out.
+  pub fn read(path: &Path) -> color_eyre::Result<Self> {
+    let read = |name: &str| {
+      let file_path = path.join(name);
+      file_path
+        .is_file()
+        .then(|| fs::read(&file_path).wrap_err_with(|| format!("failed to read {:?}", &file_path)))
+        .transpose()
+    };
+
+    Ok(Self {
+      path: path.to_path_buf(),
+      param_sfo: read(PARAM_SFO_NAME)?,
+      icon: read(ICON_NAME)?,
+    })
+  }
+
+  pub fn param_sfo_path(&self) -> PathBuf {
+    self.path.join(PARAM_SFO_NAME)
+  }
+
+  pub fn icon_path(&self) -> PathBuf {
+    self.path.join(ICON_NAME)
+  }
+}
+
+/// Resolves the save metadata from the `param.sfo` file, falling back to the save directory and the defaults.
+#[instrument(level = "debug", skip_all, fields(sce_sys = ?&sce_sys.path))]
+pub fn resolve_metadata(
+  sce_sys: &SceSysFiles,
+  save_dir: &Path,
+) -> color_eyre::Result<(SaveMetadata, MetadataSource, SceSysReport)> {
+  info!("Looking for PS4 metadata files in {:?}..", &sce_sys.path);
+  let mut report = SceSysReport {
+    path: sce_sys.path.clone(),
+    param_sfo: None,
+    icon: sce_sys.icon.as_ref().map(|_| sce_sys.icon_path()),
+  };
+
+  let (metadata, source) = match &sce_sys.param_sfo {
+    Some(param_sfo) => {
+      let sfo_path = sce_sys.param_sfo_path();
+      debug!("Parsing param.sfo file..");
+      let sfo_file =
+        SFOFile::from_bytes(param_sfo, &sfo_path).wrap_err("failed to read param.sfo file")?;
+      trace!(sfo_file = ?&sfo_file);
+      report.param_sfo = Some(sfo_path);
+
+      let metadata: SaveMetadata = sfo_file
+        .try_into()
+        .wrap_err("failed to resolve save metadata from param.sfo file")?;
+      (metadata, MetadataSource::Sfo)
+    }
+    None => {
+      warn!(
+        "No param.sfo file found in {:?}, metadata will be derived from the save directory.",
+        &sce_sys.path
+      );
+      match SaveMetadata::from_save_dir(save_dir) {
+        Some(metadata) => (metadata, MetadataSource::SaveDir),
+        None => (SaveMetadata::default(), MetadataSource::Default),
+      }
+    }
+  };
+
+  Ok((metadata, source, report))
+}
+
+/// Checks whether the save belongs to the PS4 account, which is the case when one of its directories is named
+/// after the account ID, like in the `PS4/SAVEDATA/<account ID>` directory of the USB backups and exports.
+pub fn is_account_save(dir: &Path, account: &str) -> bool {
+  dir.components().any(|component| match component {
+    Component::Normal(name) => name
+      .to_str()
+      .is_some_and(|name| name.eq_ignore_ascii_case(account)),
+    _ => false,
+  })
+}
+
+/// Collects the directories in the tree that contain a PS4 save file.
+pub fn find_save_dirs(dir: &Path, saves: &mut Vec<PathBuf>) -> std::io::Result<()> {
+  let mut has_save = false;
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    let file_type = entry.file_type()?;
+
+    if file_type.is_dir() {
+      find_save_dirs(&entry.path(), saves)?;
+    } else if file_type.is_file() && entry.file_name() == "checkpoint.dat" {
+      has_save = true;
+    }
+  }
+
+  if has_save {
+    saves.push(dir.to_path_buf());
+  }
+
+  Ok(())
+}
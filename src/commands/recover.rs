@@ -0,0 +1,124 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::WrapErr;
+use tracing::{debug, info, trace, warn};
+
+use crate::cli::RecoverArgs;
+use crate::save::SalvagedSave;
+use crate::utils;
+use crate::utils::error::KnownError;
+
+pub fn run(args: RecoverArgs) -> color_eyre::Result<()> {
+  let bytes = fs::read(&args.save_file)
+    .wrap_err_with(|| format!("failed to read save file {:?}", &args.save_file))?;
+
+  debug!("Salvaging PC save file..");
+  let salvaged = SalvagedSave::read(&bytes)
+    .wrap_err_with(|| format!("failed to salvage save file {:?}", &args.save_file))?;
+  trace!(salvaged = ?&salvaged);
+  print_salvaged(&args.save_file, &salvaged);
+
+  if let Some(output) = &args.output {
+    check_output(output, args.force)?;
+
+    let save = salvaged.rebuild(output)?;
+    trace!(save = ?&save);
+    save.write_to(output, args.allow_unknown_version)?;
+    info!("Saved the rebuilt save file to {:?}", output);
+  }
+
+  if let Some(payload_path) = &args.extract_payload {
+    check_output(payload_path, args.force)?;
+
+    if !salvaged.is_data_complete() {
+      warn!("The save data is truncated, the game may refuse to load it once converted again.");
+    }
+    fs::write(payload_path, &salvaged.data)
+      .wrap_err_with(|| format!("failed to write save data {:?}", payload_path))?;
+    info!(
+      "Extracted {} bytes of save data to {:?}",
+      salvaged.data.len(),
+      payload_path
+    );
+  }
+
+  Ok(())
+}
+
+fn check_output(path: &Path, force: bool) -> color_eyre::Result<()> {
+  if path.exists() && !force {
+    return Err(KnownError::OutputExists.report(format!("file {:?} already exists", path)));
+  }
+
+  Ok(())
+}
+
+fn print_salvaged(path: &Path, salvaged: &SalvagedSave) {
+  println!("PC save file {:?} ({} bytes)", path, salvaged.file_size);
+  println!("  Version:     {}", or_missing(salvaged.version));
+  println!("  Checksum:    {}", or_missing(salvaged.checksum));
+  println!(
+    "  Title:       {}",
+    or_missing(salvaged.title.as_ref().map(|title| format!("{:?}", title)))
+  );
+  println!(
+    "  Subtitle:    {}",
+    or_missing(
+      salvaged
+        .sub_title
+        .as_ref()
+        .map(|sub_title| format!("{:?}", sub_title))
+    )
+  );
+  match salvaged.data_length {
+    Some(length) => println!("  Save data:   {} of {} bytes", salvaged.data.len(), length),
+    None => println!("  Save data:   <missing>"),
+  }
+  match &salvaged.image {
+    Some(image) if image.is_empty() => println!("  Image:       <none>"),
+    Some(image) => match utils::image_dimensions(image) {
+      Ok((width, height)) => println!(
+        "  Image:       {}x{} ({} bytes)",
+        width,
+        height,
+        image.len()
+      ),
+      Err(_) => println!("  Image:       {} bytes", image.len()),
+    },
+    None => println!("  Image:       <missing>"),
+  }
+
+  println!("Missing or damaged");
+  if salvaged.is_complete() {
+    println!("  nothing, the save file is complete");
+  }
+  for missing in &salvaged.missing {
+    println!("  {}", missing);
+  }
+}
+
+fn or_missing<T: Display>(value: Option<T>) -> String {
+  match value {
+    Some(value) => value.to_string(),
+    None => "<missing>".to_owned(),
+  }
+}
@@ -0,0 +1,205 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+use tracing::{debug, info, trace, warn};
+
+use crate::cli::RoundtripArgs;
+use crate::commands::{resolve_metadata, resolve_save_paths, SceSysFiles};
+use crate::save::{write_output, GenerateOptions, SaveFile};
+use crate::sfo::{SFOFile, SFOParamData};
+use crate::thumbnail;
+
+#[derive(Debug)]
+enum Outcome {
+  Identical,
+  Differs(String),
+  /// The PC save file has no place to store the value.
+  NotPreserved(String),
+}
+
+pub fn run(args: RoundtripArgs) -> color_eyre::Result<()> {
+  let (sce_sys_path, save_file) = resolve_save_paths(&args.save_file)?;
+  trace!(sce_sys_path = ?&sce_sys_path, save_file = ?&save_file);
+  let save_dir = sce_sys_path
+    .parent()
+    .ok_or_else(|| eyre!("failed to resolve parent for {:?} path", &sce_sys_path))?;
+
+  let temp_dir;
+  let work_dir = match &args.keep {
+    Some(dir) => dir.clone(),
+    None => {
+      temp_dir = tempfile::tempdir().wrap_err("failed to create temporary directory")?;
+      temp_dir.path().to_path_buf()
+    }
+  };
+
+  let sce_sys = SceSysFiles::read(&sce_sys_path)?;
+  let (metadata, _, _) = resolve_metadata(&sce_sys, save_dir)?;
+  let source_params = sce_sys
+    .param_sfo
+    .as_deref()
+    .map(|param_sfo| {
+      SFOFile::from_bytes(param_sfo, sce_sys.param_sfo_path())
+        .wrap_err("failed to read param.sfo file")
+    })
+    .transpose()?
+    .map(|sfo_file| sfo_file.params);
+
+  info!("Converting the PS4 save to PC..");
+  let save_data =
+    fs::read(&save_file).wrap_err_with(|| format!("failed to read save data {:?}", &save_file))?;
+  let image = sce_sys
+    .icon
+    .clone()
+    .map(|icon| thumbnail::prepare(icon, args.raw_image))
+    .transpose()?;
+  let (save, _) = SaveFile::build(
+    &save_file,
+    save_data,
+    image,
+    metadata,
+    &GenerateOptions {
+      skip_payload_check: args.skip_payload_check,
+    },
+  )?;
+
+  let pc_dir = work_dir.join("pc");
+  fs::create_dir_all(&pc_dir)
+    .wrap_err_with(|| format!("failed to create output directory {:?}", &pc_dir))?;
+  let pc_path = pc_dir.join(save.path());
+  write_output(&pc_path, &save.to_bytes()?, true)?;
+
+  info!("Converting the PC save back to PS4..");
+  let pc_save = SaveFile::open(&pc_path).wrap_err("failed to read PC save file")?;
+  let exported = pc_save.export_ps4(work_dir.join("ps4"), true)?;
+  trace!(exported = ?&exported);
+
+  let mut outcomes = vec![(
+    "checkpoint.dat".to_owned(),
+    compare_files(&save_file, &exported.save_file)?,
+  )];
+  outcomes.push((
+    "icon0.png".to_owned(),
+    match (&sce_sys.icon, &exported.icon) {
+      // The normalization done by default is lossy by design.
+      (Some(_), Some(exported)) => match compare_files(&sce_sys.icon_path(), exported)? {
+        Outcome::Differs(details) if !args.raw_image => Outcome::NotPreserved(format!(
+          "normalized to the PC thumbnail format, {}. Use --raw-image to keep it as is",
+          details
+        )),
+        outcome => outcome,
+      },
+      _ => Outcome::NotPreserved(
+        "missing in the PS4 save, a placeholder image was generated".to_owned(),
+      ),
+    },
+  ));
+
+  match source_params {
+    Some(source_params) => {
+      debug!("Comparing param.sfo files..");
+      let exported_params = SFOFile::open(&exported.param_sfo)
+        .wrap_err("failed to read the exported param.sfo file")?
+        .params;
+      outcomes.extend(compare_params(&source_params, &exported_params));
+    }
+    None => outcomes.push((
+      "param.sfo".to_owned(),
+      Outcome::NotPreserved("missing in the PS4 save, the metadata was derived".to_owned()),
+    )),
+  }
+
+  println!("Round trip of {:?}", &save_file);
+  for (name, outcome) in &outcomes {
+    match outcome {
+      Outcome::Identical => println!("  {:<20} identical", name),
+      Outcome::Differs(details) => println!("  {:<20} differs, {}", name, details),
+      Outcome::NotPreserved(reason) => println!("  {:<20} not preserved, {}", name, reason),
+    }
+  }
+
+  if let Some(dir) = &args.keep {
+    info!("Kept the intermediate saves in {:?}", dir);
+  }
+
+  let not_preserved = outcomes
+    .iter()
+    .filter(|(_, outcome)| matches!(outcome, Outcome::NotPreserved(_)))
+    .count();
+  if not_preserved > 0 {
+    warn!(
+      "{} of the PS4 save fields could not be preserved by the PC save file.",
+      not_preserved
+    );
+  }
+
+  let differs = outcomes
+    .iter()
+    .filter(|(_, outcome)| matches!(outcome, Outcome::Differs(_)))
+    .count();
+  if differs > 0 {
+    bail!(
+      "round trip changed {} of the {} compared fields",
+      differs,
+      outcomes.len() - not_preserved
+    );
+  }
+
+  info!("Round trip didn't change any of the compared fields.");
+  Ok(())
+}
+
+fn compare_files(source: &Path, exported: &Path) -> color_eyre::Result<Outcome> {
+  let read = |path: &Path| fs::read(path).wrap_err_with(|| format!("failed to read {:?}", path));
+  let (source, exported) = (read(source)?, read(exported)?);
+
+  Ok(if source == exported {
+    Outcome::Identical
+  } else {
+    Outcome::Differs(format!(
+      "{} bytes before and {} bytes after",
+      source.len(),
+      exported.len()
+    ))
+  })
+}
+
+/// Compares the params the PC save could store, the rest is reported as not preserved.
+fn compare_params(
+  source: &HashMap<String, SFOParamData>,
+  exported: &HashMap<String, SFOParamData>,
+) -> Vec<(String, Outcome)> {
+  let source = source.iter().collect::<BTreeMap<_, _>>();
+
+  source
+    .into_iter()
+    .map(|(key, value)| {
+      let outcome = match exported.get(key) {
+        Some(exported) if exported == value => Outcome::Identical,
+        Some(exported) => Outcome::Differs(format!("{} before and {} after", value, exported)),
+        None => Outcome::NotPreserved("the PC save file has no place for it".to_owned()),
+      };
+
+      (key.clone(), outcome)
+    })
+    .collect()
+}
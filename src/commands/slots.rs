@@ -0,0 +1,171 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::cmp::Reverse;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::WrapErr;
+use tracing::{debug, trace, warn};
+
+use crate::archive::{read_saves, ArchiveKind};
+use crate::cli::macros::clap_error;
+use crate::cli::SlotsArgs;
+use crate::commands::find_save_dirs;
+use crate::save::{SaveFile, SaveMetadata, SaveTitle, GGDS_MAGIC};
+use crate::sfo::SFOFile;
+use crate::utils::error::KnownError;
+
+/// Save slot found in the directory or the archive.
+struct Slot {
+  format: &'static str,
+  metadata: SaveMetadata,
+  path: PathBuf,
+}
+
+pub fn run(args: SlotsArgs) -> color_eyre::Result<()> {
+  let mut slots = if ArchiveKind::from_path(&args.path).is_some() {
+    archive_slots(&args.path)?
+  } else if args.path.is_dir() {
+    dir_slots(&args.path)?
+  } else {
+    clap_error!(
+      "path {:?} is not a directory or a .zip/.tar/.tar.gz archive",
+      &args.path
+    );
+  };
+
+  if slots.is_empty() {
+    return Err(KnownError::WrongFolder.report(format!("no save slots found in {:?}", &args.path)));
+  }
+
+  // The furthest progressed saves come first, the ones with titles we can't parse at the end.
+  slots.sort_by_cached_key(|slot| {
+    let playtime = slot
+      .metadata
+      .title
+      .parse::<SaveTitle>()
+      .ok()
+      .map(|title| title.playtime);
+
+    (
+      playtime.is_none(),
+      Reverse(playtime),
+      slot.metadata.file_name.clone(),
+      slot.path.clone(),
+    )
+  });
+  for slot in slots {
+    println!(
+      "{:<14} {:<4} {:?} ({})",
+      slot.metadata.file_name.to_uppercase(),
+      slot.format,
+      slot.metadata.title,
+      slot.metadata.sub_title
+    );
+    println!("  {:?}", slot.path);
+  }
+
+  Ok(())
+}
+
+/// Finds the PS4 save directories in the tree, and the PC saves directly in the directory.
+fn dir_slots(dir: &Path) -> color_eyre::Result<Vec<Slot>> {
+  let mut save_dirs = vec![];
+  find_save_dirs(dir, &mut save_dirs)
+    .wrap_err_with(|| format!("failed to scan directory {:?}", dir))?;
+
+  let mut slots = vec![];
+  for save_dir in save_dirs {
+    let sfo_path = save_dir.join("sce_sys").join("param.sfo");
+    let metadata = if sfo_path.is_file() {
+      SFOFile::open(&sfo_path)
+        .and_then(|sfo_file| sfo_file.try_into())
+        .wrap_err_with(|| format!("failed to read param.sfo file {:?}", &sfo_path))
+    } else {
+      Ok(SaveMetadata::from_save_dir(&save_dir).unwrap_or_default())
+    };
+
+    match metadata {
+      Ok(metadata) => slots.push(Slot {
+        format: "PS4",
+        metadata,
+        path: save_dir,
+      }),
+      Err(err) => warn!("Skipping PS4 save {:?}: {:#}", &save_dir, err),
+    }
+  }
+
+  let entries =
+    fs::read_dir(dir).wrap_err_with(|| format!("failed to read directory {:?}", dir))?;
+  for entry in entries {
+    let path = entry.wrap_err("failed to read directory entry")?.path();
+    if !path.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some("dat") {
+      continue;
+    }
+
+    if !has_ggds_magic(&path) {
+      debug!("Skipping {:?}, it's not a PC save file", &path);
+      continue;
+    }
+
+    match SaveFile::open(&path).and_then(|save| save.metadata()) {
+      Ok(metadata) => slots.push(Slot {
+        format: "PC",
+        metadata,
+        path,
+      }),
+      Err(err) => warn!("Skipping PC save {:?}: {:#}", &path, err),
+    }
+  }
+
+  Ok(slots)
+}
+
+/// Reads the metadata of the PS4 saves in the archive, without extracting it.
+fn archive_slots(archive: &Path) -> color_eyre::Result<Vec<Slot>> {
+  let mut slots = vec![];
+  for save in read_saves(archive)? {
+    let path = archive.join(&save.dir);
+    let metadata = match &save.param_sfo {
+      Some(param_sfo) => SFOFile::from_bytes(param_sfo, archive.join(save.param_sfo_path()))
+        .and_then(|sfo_file| sfo_file.try_into())
+        .wrap_err("failed to read param.sfo file"),
+      None => Ok(SaveMetadata::from_save_dir(&save.dir).unwrap_or_default()),
+    };
+    trace!(path = ?&path, metadata = ?&metadata);
+
+    match metadata {
+      Ok(metadata) => slots.push(Slot {
+        format: "PS4",
+        metadata,
+        path,
+      }),
+      Err(err) => warn!("Skipping PS4 save {:?}: {:#}", &path, err),
+    }
+  }
+
+  Ok(slots)
+}
+
+fn has_ggds_magic(path: &Path) -> bool {
+  let mut magic = [0; 4];
+  File::open(path)
+    .and_then(|mut file| file.read_exact(&mut magic))
+    .is_ok_and(|_| magic == GGDS_MAGIC)
+}
@@ -0,0 +1,100 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use color_eyre::eyre::{bail, WrapErr};
+use tracing::{debug, error, info, trace, warn};
+
+use crate::cli::VerifyArgs;
+use crate::commands::resolve_save_paths;
+use crate::payload::{check_payload, validate_payload};
+use crate::save::{SaveFile, SaveMetadata, SUPPORTED_VERSION};
+use crate::sfo::SFOFile;
+use crate::utils;
+
+pub fn run(args: VerifyArgs) -> color_eyre::Result<()> {
+  debug!("Parsing PC save file..");
+  let mut save = SaveFile::open(&args.save_file).wrap_err("failed to read PC save file")?;
+  trace!(save = ?&save);
+
+  let mut problems = vec![];
+
+  if save.version != SUPPORTED_VERSION {
+    problems.push(format!(
+      "unknown save file version {}, expected {}",
+      save.version, SUPPORTED_VERSION
+    ));
+  }
+
+  if let Err(err) =
+    validate_payload(&save.data).and_then(|_| check_payload(save.path(), &save.data))
+  {
+    problems.push(format!("{:#}", err));
+  }
+
+  if !save.trailing.is_empty() {
+    warn!(
+      "Save file has {} bytes of unknown data after the image.",
+      save.trailing.len()
+    );
+  }
+
+  if save.image.is_empty() {
+    warn!("Save file has no image, the game will show it without a thumbnail.");
+  } else if let Err(err) = utils::image_dimensions(&save.image) {
+    problems.push(format!("{:#}", err));
+  }
+
+  if let Some(source) = &args.source {
+    let (sce_sys_path, _) = resolve_save_paths(source)?;
+
+    debug!("Parsing param.sfo file..");
+    let metadata: SaveMetadata = SFOFile::open(sce_sys_path.join("param.sfo"))
+      .wrap_err("failed to read param.sfo file")?
+      .try_into()
+      .wrap_err("failed to resolve save metadata from param.sfo file")?;
+    trace!(metadata = ?&metadata);
+
+    if save.checksum == metadata.checksum {
+      info!("Checksum {} matches the PS4 save file.", save.checksum);
+    } else if args.fix_checksum {
+      info!(
+        "Rewriting checksum {} to {} from the PS4 save file..",
+        save.checksum, metadata.checksum
+      );
+      save.checksum = metadata.checksum;
+      save.write_to(save.path(), args.allow_unknown_version)?;
+    } else {
+      problems.push(format!(
+        "checksum {} doesn't match {} from the PS4 save file, use --fix-checksum to rewrite it",
+        save.checksum, metadata.checksum
+      ));
+    }
+  } else {
+    warn!("No PS4 save file provided, the checksum can't be verified. Use --source to verify it.");
+  }
+
+  if !problems.is_empty() {
+    for problem in &problems {
+      error!("{}", problem);
+    }
+
+    bail!("save file {:?} failed verification", save.path());
+  }
+
+  info!("Save file {:?} is valid.", save.path());
+  Ok(())
+}
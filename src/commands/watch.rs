@@ -0,0 +1,236 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use color_eyre::eyre::{bail, WrapErr};
+use tracing::{debug, error, info, trace, warn};
+
+use crate::archive::ArchiveKind;
+use crate::cli::macros::clap_error;
+use crate::cli::{ConvertOptions, ImageArgs, WatchArgs};
+use crate::commands::convert::convert_save;
+use crate::commands::{find_save_dirs, is_account_save};
+use crate::report::ConversionStatus;
+use crate::save::SaveFile;
+use crate::utils;
+
+/// Size and number of the files in a save directory, the save is considered copied once it stops changing.
+type Fingerprint = (u64, usize);
+
+pub fn run(args: WatchArgs) -> color_eyre::Result<()> {
+  if !args.dir.is_dir() {
+    clap_error!("path {:?} is not a directory", &args.dir);
+  }
+  let Some(output_dir) = args.output_dir else {
+    clap_error!("no output directory passed with --output or set in the config file");
+  };
+  if output_dir == Path::new("-") || ArchiveKind::from_path(&output_dir).is_some() {
+    clap_error!("watch mode can only write the generated save files into a directory");
+  }
+
+  let convert_args = ConvertOptions {
+    output_dir: Some(output_dir.clone()),
+    force: args.force,
+    backups: args.backups,
+    account: args.account,
+    skip_payload_check: args.skip_payload_check,
+    image: ImageArgs {
+      raw_image: args.raw_image,
+      ..ImageArgs::default()
+    },
+    ..ConvertOptions::default()
+  };
+  let mut watcher = Watcher {
+    converted: converted_payloads(&output_dir)?,
+    ..Watcher::default()
+  };
+
+  info!("Watching {:?} for new PS4 saves..", &args.dir);
+  loop {
+    watcher.scan(&args.dir, &convert_args)?;
+    if args.once && watcher.pending.is_empty() {
+      break;
+    }
+
+    thread::sleep(Duration::from_secs(args.interval));
+  }
+
+  Ok(())
+}
+
+#[derive(Default)]
+struct Watcher {
+  /// Save directories that are still being copied, with the fingerprint from the last scan.
+  pending: HashMap<PathBuf, Fingerprint>,
+  /// Save directories that were already handled, with the fingerprint they were handled at.
+  handled: HashMap<PathBuf, Fingerprint>,
+  /// SHA-256 hashes of the save data that was already converted.
+  converted: HashSet<String>,
+}
+
+impl Watcher {
+  fn scan(&mut self, dir: &Path, args: &ConvertOptions) -> color_eyre::Result<()> {
+    let mut saves = vec![];
+    find_save_dirs(dir, &mut saves)
+      .wrap_err_with(|| format!("failed to scan directory {:?}", dir))?;
+    if let Some(account) = &args.account {
+      saves.retain(|save_dir| is_account_save(save_dir, account));
+    }
+    self.pending.retain(|path, _| saves.contains(path));
+
+    for save_dir in saves {
+      let fingerprint = match fingerprint(&save_dir) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => {
+          // The directory is most likely being moved or removed while we look at it.
+          debug!("Unable to read save directory {:?}: {}", &save_dir, err);
+          continue;
+        }
+      };
+      trace!(save_dir = ?&save_dir, fingerprint = ?fingerprint);
+
+      if self.handled.get(&save_dir) == Some(&fingerprint) {
+        continue;
+      }
+
+      if self.pending.get(&save_dir) != Some(&fingerprint) {
+        if self.pending.insert(save_dir.clone(), fingerprint).is_none() {
+          debug!(
+            "Found PS4 save {:?}, waiting for it to finish copying..",
+            &save_dir
+          );
+        }
+        continue;
+      }
+
+      self.pending.remove(&save_dir);
+      self.handled.insert(save_dir.clone(), fingerprint);
+      self.convert(&save_dir, args);
+    }
+
+    Ok(())
+  }
+
+  fn convert(&mut self, save_dir: &Path, args: &ConvertOptions) {
+    let payload_sha256 = match fs::read(save_dir.join("checkpoint.dat")) {
+      Ok(data) => utils::sha256_hex(&data),
+      Err(err) => {
+        error!("Failed to read PS4 save {:?}: {}", save_dir, err);
+        return;
+      }
+    };
+    if self.converted.contains(&payload_sha256) {
+      info!(
+        "Skipping PS4 save {:?}, it was already converted.",
+        save_dir
+      );
+      return;
+    }
+
+    let (report, result) = convert_save(save_dir, args);
+    match (report.status, result) {
+      (_, Err(err)) => error!("Failed to convert {:?}: {:#}", save_dir, err),
+      (ConversionStatus::Converted, Ok(())) => {
+        let output = report.output.expect("converted save should have an output");
+        info!("Converted {:?} into {:?}", save_dir, &output.path);
+        self.converted.insert(output.payload_sha256);
+      }
+      _ => {}
+    }
+  }
+}
+
+fn fingerprint(dir: &Path) -> std::io::Result<Fingerprint> {
+  let mut total = (0, 0);
+  for entry in fs::read_dir(dir)? {
+    let entry = entry?;
+    let metadata = entry.metadata()?;
+
+    if metadata.is_dir() {
+      let (size, count) = fingerprint(&entry.path())?;
+      total.0 += size;
+      total.1 += count;
+    } else {
+      total.0 += metadata.len();
+      total.1 += 1;
+    }
+  }
+
+  Ok(total)
+}
+
+/// Hashes the save data of the PC saves already in the output directory, so the saves converted
+/// before the watcher was started are not converted again.
+fn converted_payloads(output_dir: &Path) -> color_eyre::Result<HashSet<String>> {
+  let mut payloads = HashSet::new();
+  if !output_dir.exists() {
+    return Ok(payloads);
+  } else if !output_dir.is_dir() {
+    bail!("output path is not a directory");
+  }
+
+  let entries = fs::read_dir(output_dir)
+    .wrap_err_with(|| format!("failed to read output directory {:?}", output_dir))?;
+  for entry in entries {
+    let path = entry
+      .wrap_err("failed to read output directory entry")?
+      .path();
+    if path.extension().and_then(|ext| ext.to_str()) != Some("dat") {
+      continue;
+    }
+
+    match SaveFile::open(&path) {
+      Ok(save) => {
+        payloads.insert(utils::sha256_hex(&save.data));
+      }
+      Err(err) => warn!("Unable to read PC save {:?}: {:#}", &path, err),
+    }
+  }
+  debug!(
+    "Found {} already converted saves in the output directory",
+    payloads.len()
+  );
+
+  Ok(payloads)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::utils::error::KnownError;
+
+  #[test]
+  fn removed_saves_fail_without_stopping_the_watcher() {
+    let dir = tempfile::tempdir().unwrap();
+    let args = ConvertOptions {
+      output_dir: Some(dir.path().join("pc")),
+      ..ConvertOptions::default()
+    };
+
+    // The save directory was removed between the scan and the conversion.
+    let (report, result) = convert_save(&dir.path().join("AUTOSAVE0"), &args);
+    assert!(matches!(report.status, ConversionStatus::Failed));
+    let err = result.unwrap_err();
+    assert_eq!(KnownError::find(&err), Some(KnownError::WrongFolder));
+    assert!(format!("{:#}", err).contains("doesn't exist"));
+  }
+}
@@ -0,0 +1,153 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::log::{ColorMode, LogFormat};
+
+static CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Default options read from the config file, explicit CLI flags always take precedence over them.
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  /// Path the config was loaded from, `None` if no config file was found.
+  #[serde(skip)]
+  pub path: Option<PathBuf>,
+  pub convert: ConvertConfig,
+  pub log: LogConfig,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConvertConfig {
+  /// Directory where the generated save files are stored.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub output_dir: Option<PathBuf>,
+  /// Overwrite the output files if they already exist.
+  pub force: bool,
+  /// Embed the save images as is, without converting them to the PC thumbnail format, also used by the edit command.
+  pub raw_image: bool,
+  /// Number of saves converted in parallel from an archive.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub jobs: Option<NonZeroUsize>,
+  /// Number of previous copies kept when an output file is overwritten.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub backups: Option<usize>,
+  /// PS4 account ID whose saves are converted from archives and watched directories.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub account: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+  /// Console log level, used when neither the verbosity flags nor `RUST_LOG` are passed.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub level: Option<LogLevel>,
+  pub format: LogFormat,
+  pub color: ColorMode,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+  Error,
+  Warn,
+  Info,
+  Debug,
+  Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+  fn from(level: LogLevel) -> Self {
+    match level {
+      LogLevel::Error => LevelFilter::ERROR,
+      LogLevel::Warn => LevelFilter::WARN,
+      LogLevel::Info => LevelFilter::INFO,
+      LogLevel::Debug => LevelFilter::DEBUG,
+      LogLevel::Trace => LevelFilter::TRACE,
+    }
+  }
+}
+
+impl From<LevelFilter> for LogLevel {
+  fn from(level: LevelFilter) -> Self {
+    match level {
+      LevelFilter::TRACE => LogLevel::Trace,
+      LevelFilter::DEBUG => LogLevel::Debug,
+      LevelFilter::INFO => LogLevel::Info,
+      LevelFilter::WARN => LogLevel::Warn,
+      _ => LogLevel::Error,
+    }
+  }
+}
+
+/// Error returned when the config file can't be loaded. It's a plain error instead of a [`color_eyre::Report`],
+/// as the config is loaded before the error hook is installed.
+#[derive(Debug)]
+pub struct ConfigError {
+  path: PathBuf,
+  message: String,
+}
+
+impl Display for ConfigError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "failed to load config file {:?}: {}",
+      self.path, self.message
+    )
+  }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+  /// Loads the config from the given path, or from the default location if it exists there.
+  pub fn load(path: Option<&Path>) -> Result<Self, ConfigError> {
+    let path = match path {
+      Some(path) => path.to_path_buf(),
+      None => match default_path() {
+        Some(path) if path.is_file() => path,
+        _ => return Ok(Config::default()),
+      },
+    };
+
+    let error = |message: String| ConfigError {
+      path: path.clone(),
+      message: message.trim_end().to_owned(),
+    };
+    let content = fs::read_to_string(&path).map_err(|err| error(err.to_string()))?;
+    let mut config: Config = toml::from_str(&content).map_err(|err| error(err.to_string()))?;
+    config.path = Some(path);
+
+    Ok(config)
+  }
+}
+
+/// Path of the config file in the user's config directory, e.g. `$XDG_CONFIG_HOME/hfw-save-converter/config.toml`.
+pub fn default_path() -> Option<PathBuf> {
+  dirs::config_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(CONFIG_FILE_NAME))
+}
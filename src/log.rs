@@ -15,14 +15,34 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::cell::RefCell;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
+use clap::ValueEnum;
+use color_eyre::eyre::WrapErr;
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use tiny_gradient::{GradientStr, RGB};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{debug, Event, Level, Subscriber};
 use tracing_error::ErrorLayer;
-use tracing_subscriber::filter::LevelFilter;
-use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::filter::{filter_fn, EnvFilter, LevelFilter};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{Layer, Registry};
+
+use crate::progress;
+
+static CRATE_NAME: &str = env!("CARGO_CRATE_NAME");
 
 static LOGO: &str = r#"  _  _ _____      __  ___   ___   _____    ___ ___  _  ___   _____ ___ _____ ___ ___
  | || | __\ \    / / / __| /_\ \ / / __|  / __/ _ \| \| \ \ / / __| _ \_   _| __| _ \
@@ -34,9 +54,79 @@ lazy_static! {
   static ref COLORS: [RGB; 6] = ["#add7ff", "#89ddff", "#5de4c7", "#fae4fc", "#d0679d", "#fffac2",]
     .map(RGB::from_str)
     .map(|v| v.unwrap());
+  static ref TIMINGS: Mutex<Vec<StageTiming>> = Mutex::new(vec![]);
+}
+
+thread_local! {
+  // Kept per thread, so the saves converted in parallel don't get each other's warnings.
+  static WARNINGS: RefCell<Vec<String>> = const { RefCell::new(vec![]) };
+}
+
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+  /// Human readable, single line per event.
+  #[default]
+  Text,
+  /// One JSON object per event, including the span context.
+  Json,
+}
+
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorMode {
+  /// Colorize the output when writing to a terminal, honouring `NO_COLOR` and `CLICOLOR`.
+  #[default]
+  Auto,
+  Always,
+  Never,
 }
 
-pub fn setup_tracing(level: tracing::Level) -> color_eyre::Result<()> {
+impl ColorMode {
+  /// Checks whether the output written to the stream should be colorized.
+  pub fn enabled<T: IsTerminal>(self, stream: &T) -> bool {
+    match self {
+      ColorMode::Always => true,
+      ColorMode::Never => false,
+      ColorMode::Auto => {
+        let var = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+
+        if var("NO_COLOR").is_some() {
+          false
+        } else if var("CLICOLOR_FORCE").is_some_and(|value| value != "0") {
+          true
+        } else if var("CLICOLOR").is_some_and(|value| value == "0") {
+          false
+        } else {
+          stream.is_terminal()
+        }
+      }
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct LogOptions<'a> {
+  /// Console log level, `None` falls back to the `RUST_LOG` variable.
+  pub level: Option<LevelFilter>,
+  /// Console log level used when the `RUST_LOG` variable isn't set either.
+  pub default_level: Option<LevelFilter>,
+  /// Additional filter directives, applied on top of the console log level.
+  pub filter: Option<&'a str>,
+  pub format: LogFormat,
+  /// Whether the console output should be colorized.
+  pub ansi: bool,
+  /// File receiving the full-detail log, regardless of the console log level.
+  pub file: Option<&'a Path>,
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Sets up the logging, the level passed through the CLI takes precedence over the `RUST_LOG` variable,
+/// and the filter directives are applied on top of both.
+pub fn setup_tracing(options: LogOptions) -> color_eyre::Result<()> {
   // For this app, I define three types of log levels:
   // 1. INFO – Used to inform the user about the relevant actions that the program is performing.
   //           For example it will log that the save was generated to a location X
@@ -45,24 +135,243 @@ pub fn setup_tracing(level: tracing::Level) -> color_eyre::Result<()> {
   // 3. TRACE – Used to debug the application in case of a failure.
   //           For example it will log outputs of the functions that it performs or steps it took to parse the file.
 
-  let fmt_layer = tracing_subscriber::fmt::layer()
-    .with_target(false)
-    .compact();
+  let mut env_filter = match options.level {
+    Some(level) => EnvFilter::default().add_directive(level.into()),
+    None => EnvFilter::builder()
+      .with_default_directive(options.default_level.unwrap_or(LevelFilter::INFO).into())
+      .from_env()
+      .wrap_err("invalid RUST_LOG environment variable")?,
+  };
 
-  let level_filter = LevelFilter::from_level(level);
+  for directive in options
+    .filter
+    .into_iter()
+    .flat_map(|filter| filter.split(','))
+    .map(str::trim)
+    .filter(|directive| !directive.is_empty())
+  {
+    env_filter = env_filter.add_directive(
+      qualify_directive(directive)
+        .parse()
+        .wrap_err_with(|| format!("invalid log filter directive {:?}", directive))?,
+    );
+  }
+
+  INTERACTIVE.store(
+    options.format == LogFormat::Text
+      && options.level != Some(LevelFilter::ERROR)
+      && std::io::stderr().is_terminal(),
+    Ordering::Relaxed,
+  );
+
+  let mut layers: Vec<BoxedLayer> = vec![match options.format {
+    LogFormat::Text => tracing_subscriber::fmt::layer()
+      .with_writer(|| ConsoleWriter)
+      .with_ansi(options.ansi)
+      .with_target(false)
+      .compact()
+      .with_filter(env_filter)
+      .boxed(),
+    LogFormat::Json => tracing_subscriber::fmt::layer()
+      .with_writer(|| ConsoleWriter)
+      .json()
+      .with_current_span(true)
+      .with_span_list(true)
+      .with_filter(env_filter)
+      .boxed(),
+  }];
+
+  if let Some(path) = options.file {
+    layers.push(create_file_layer(path, options.format)?);
+  }
+
+  // Without their own filters these layers would enable every callsite, including the trace ones.
+  // The timings are always measured, the summary is a debug event filtered like any other.
+  layers.push(
+    TimingsLayer
+      .with_filter(filter_fn(|metadata| metadata.is_span()))
+      .boxed(),
+  );
+  layers.push(WarningsLayer.with_filter(LevelFilter::WARN).boxed());
 
   Ok(
     tracing_subscriber::registry()
-      .with(level_filter)
-      .with(fmt_layer)
+      .with(layers)
       .with(ErrorLayer::default())
       .try_init()?,
   )
 }
 
-pub fn print_logo() {
-  match std::env::var("NO_COLOR") {
-    Ok(_) => println!("{}", LOGO),
-    Err(_) => println!("{}", LOGO.gradient(*COLORS)),
+/// Creates a layer writing every event of this application to the file, with the full event context.
+fn create_file_layer(path: &Path, format: LogFormat) -> color_eyre::Result<BoxedLayer> {
+  let file =
+    File::create(path).wrap_err_with(|| format!("failed to create log file {:?}", path))?;
+  let filter = EnvFilter::default()
+    .add_directive(LevelFilter::INFO.into())
+    .add_directive(format!("{}=trace", CRATE_NAME).parse()?);
+
+  let layer = tracing_subscriber::fmt::layer()
+    .with_writer(Mutex::new(file))
+    .with_ansi(false)
+    .with_file(true)
+    .with_line_number(true)
+    .with_thread_names(true);
+
+  Ok(match format {
+    LogFormat::Text => layer.with_filter(filter).boxed(),
+    LogFormat::Json => layer
+      .json()
+      .with_current_span(true)
+      .with_span_list(true)
+      .with_filter(filter)
+      .boxed(),
+  })
+}
+
+/// Prefixes the directive target with the crate name, so modules can be referenced as `sfo=trace`.
+fn qualify_directive(directive: &str) -> String {
+  let target = directive.split(['=', '[']).next().unwrap_or_default();
+
+  if target.is_empty()
+    || target.contains("::")
+    || target == CRATE_NAME
+    || (target == directive && target.parse::<LevelFilter>().is_ok())
+  {
+    directive.to_owned()
+  } else {
+    format!("{}::{}", CRATE_NAME, directive)
+  }
+}
+
+/// Returns the warnings logged on this thread since the last call, so they can be included in the reports.
+pub fn take_warnings() -> Vec<String> {
+  WARNINGS.with_borrow_mut(std::mem::take)
+}
+
+/// Runs the function with only the warnings being collected, returning them with its result.
+#[cfg(test)]
+pub fn capture_warnings<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+  take_warnings();
+  let subscriber = Registry::default().with(WarningsLayer);
+  let result = tracing::subscriber::with_default(subscriber, f);
+
+  (result, take_warnings())
+}
+
+/// Checks whether the console logs are read by a person, in which case progress bars can be shown.
+pub fn is_interactive() -> bool {
+  INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Logs the time spent in each of the pipeline stages, in the order they were first entered.
+pub fn log_timings() {
+  let timings = std::mem::take(&mut *TIMINGS.lock().unwrap());
+  if timings.is_empty() {
+    return;
+  }
+
+  debug!("Timing summary:");
+  for timing in timings {
+    debug!(
+      "  {:<24} {:>10.2?} ({} {})",
+      timing.name,
+      timing.total,
+      timing.calls,
+      if timing.calls == 1 { "call" } else { "calls" }
+    );
+  }
+}
+
+#[derive(Debug)]
+struct StageTiming {
+  name: &'static str,
+  calls: u32,
+  total: Duration,
+}
+
+/// Measures the time between the creation and the closing of every span, accumulated per span name.
+struct TimingsLayer;
+
+impl<S> Layer<S> for TimingsLayer
+where
+  S: Subscriber + for<'a> LookupSpan<'a>,
+{
+  fn on_new_span(&self, _attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+    if let Some(span) = ctx.span(id) {
+      span.extensions_mut().insert(Instant::now());
+
+      let mut timings = TIMINGS.lock().unwrap();
+      if !timings.iter().any(|timing| timing.name == span.name()) {
+        timings.push(StageTiming {
+          name: span.name(),
+          calls: 0,
+          total: Duration::ZERO,
+        });
+      }
+    }
+  }
+
+  fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+    let Some(span) = ctx.span(&id) else {
+      return;
+    };
+    let Some(started) = span.extensions().get::<Instant>().copied() else {
+      return;
+    };
+
+    if let Some(timing) = TIMINGS
+      .lock()
+      .unwrap()
+      .iter_mut()
+      .find(|timing| timing.name == span.name())
+    {
+      timing.calls += 1;
+      timing.total += started.elapsed();
+    }
+  }
+}
+
+struct WarningsLayer;
+
+impl<S: Subscriber> Layer<S> for WarningsLayer {
+  fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+    if *event.metadata().level() == Level::WARN {
+      let mut visitor = MessageVisitor::default();
+      event.record(&mut visitor);
+      WARNINGS.with_borrow_mut(|warnings| warnings.push(visitor.0));
+    }
+  }
+}
+
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+  fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+    if field.name() == "message" {
+      self.0 = format!("{:?}", value);
+    }
+  }
+}
+
+/// Writes the console logs to the standard error, above the progress bar if one is shown.
+struct ConsoleWriter;
+
+impl Write for ConsoleWriter {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    progress::suspend(|| std::io::stderr().write_all(buf))?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    std::io::stderr().flush()
+  }
+}
+
+pub fn print_logo(color: bool) {
+  if color {
+    eprintln!("{}", LOGO.gradient(*COLORS));
+  } else {
+    eprintln!("{}", LOGO);
   }
 }
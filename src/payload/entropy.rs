@@ -0,0 +1,155 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+static BLOCK_SIZE: usize = 0x1000;
+/// Below this many bytes the entropy says too little about the data to tell random bytes apart,
+/// so shorter payloads and trailing blocks are not measured.
+static MIN_BLOCK_SIZE: usize = 0x100;
+/// Random data over a 4 KiB block measures around 7.95 bits per byte,
+/// while structured save data has plenty of blocks well below that.
+static BLOCK_ENTROPY_MARGIN: f64 = 0.055;
+static ENCRYPTED_BLOCK_RATIO: f64 = 0.98;
+
+#[derive(Debug)]
+pub struct EntropyStats {
+  pub average: f64,
+  pub blocks: usize,
+  pub high_entropy_blocks: usize,
+}
+
+impl EntropyStats {
+  pub fn looks_encrypted(&self) -> bool {
+    self.blocks > 0 && self.high_entropy_blocks as f64 / self.blocks as f64 >= ENCRYPTED_BLOCK_RATIO
+  }
+}
+
+/// Measures the Shannon entropy of every block of the data,
+/// including the trailing one when it's at least [`MIN_BLOCK_SIZE`] bytes long.
+pub fn measure(data: &[u8]) -> EntropyStats {
+  let entropies = data
+    .chunks(BLOCK_SIZE)
+    .filter(|block| block.len() >= MIN_BLOCK_SIZE)
+    .map(|block| (shannon_entropy(block), block_threshold(block.len())))
+    .collect::<Vec<_>>();
+
+  EntropyStats {
+    average: if entropies.is_empty() {
+      shannon_entropy(data)
+    } else {
+      entropies.iter().map(|(entropy, _)| entropy).sum::<f64>() / entropies.len() as f64
+    },
+    blocks: entropies.len(),
+    high_entropy_blocks: entropies
+      .iter()
+      .filter(|(entropy, threshold)| entropy >= threshold)
+      .count(),
+  }
+}
+
+/// Entropy above which a block of the given length is considered random.
+///
+/// Fewer bytes can't cover all the 256 values evenly, so random data measures lower the shorter it is.
+/// The expected entropy is approximated with the Miller-Madow bias correction, and the margin
+/// grows for shorter blocks as their entropy varies more.
+fn block_threshold(len: usize) -> f64 {
+  let len = len as f64;
+  let expected = 8.0 - 255.0 / (2.0 * len * std::f64::consts::LN_2);
+
+  expected - BLOCK_ENTROPY_MARGIN * BLOCK_SIZE as f64 / len
+}
+
+fn shannon_entropy(data: &[u8]) -> f64 {
+  let mut counts = [0usize; 256];
+  for byte in data {
+    counts[*byte as usize] += 1;
+  }
+
+  let len = data.len() as f64;
+  counts
+    .iter()
+    .filter(|count| **count > 0)
+    .map(|count| {
+      let p = *count as f64 / len;
+      -p * p.log2()
+    })
+    .sum()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Deterministic xorshift stream standing in for encrypted data.
+  fn random_bytes(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..len)
+      .map(|_| {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 56) as u8
+      })
+      .collect()
+  }
+
+  #[test]
+  fn random_bytes_look_encrypted() {
+    let stats = measure(&random_bytes(BLOCK_SIZE * 8));
+    assert_eq!(stats.blocks, 8);
+    assert_eq!(stats.high_entropy_blocks, 8);
+    assert!(stats.average > 7.9, "{:?}", stats);
+    assert!(stats.looks_encrypted());
+  }
+
+  #[test]
+  fn zeros_dont_look_encrypted() {
+    let stats = measure(&vec![0; BLOCK_SIZE * 8]);
+    assert_eq!(stats.blocks, 8);
+    assert_eq!(stats.high_entropy_blocks, 0);
+    assert_eq!(stats.average, 0.0);
+    assert!(!stats.looks_encrypted());
+  }
+
+  #[test]
+  fn short_random_data_looks_encrypted() {
+    for len in [MIN_BLOCK_SIZE, 1000, BLOCK_SIZE - 1] {
+      let stats = measure(&random_bytes(len));
+      assert_eq!(stats.blocks, 1, "{}", len);
+      assert!(stats.looks_encrypted(), "{}: {:?}", len, stats);
+    }
+
+    // The trailing block counts as any other one.
+    let mut data = random_bytes(BLOCK_SIZE + 600);
+    let stats = measure(&data);
+    assert_eq!(stats.blocks, 2);
+    assert!(stats.looks_encrypted(), "{:?}", stats);
+
+    data[BLOCK_SIZE..].fill(b'a');
+    assert!(!measure(&data).looks_encrypted());
+  }
+
+  #[test]
+  fn too_short_data_is_not_measured() {
+    let stats = measure(&random_bytes(MIN_BLOCK_SIZE - 1));
+    assert_eq!(stats.blocks, 0);
+    assert!(!stats.looks_encrypted());
+
+    let stats = measure(&[]);
+    assert_eq!(stats.blocks, 0);
+    assert_eq!(stats.average, 0.0);
+  }
+}
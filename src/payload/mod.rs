@@ -0,0 +1,149 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::path::Path;
+
+use color_eyre::eyre::bail;
+use tracing::{debug, trace};
+
+pub use entropy::EntropyStats;
+pub use title::find_title;
+
+use crate::save::GGDS_MAGIC;
+use crate::sfo::SFO_MAGIC;
+use crate::utils::error::KnownError;
+
+mod entropy;
+mod title;
+
+static PNG_MAGIC: &[u8] = b"\x89PNG";
+static ENCRYPTED_IMAGE_PREFIX: &str = "sdimg_";
+
+#[derive(Debug)]
+pub struct PayloadInfo {
+  pub size: usize,
+  pub entropy: EntropyStats,
+}
+
+impl PayloadInfo {
+  pub fn new(data: &[u8]) -> Self {
+    Self {
+      size: data.len(),
+      entropy: entropy::measure(data),
+    }
+  }
+}
+
+/// Validates that the payload can be stored in a GGDS file.
+///
+/// The layout of the decrypted checkpoint data is not documented,
+/// so this only asserts what the GGDS format itself requires from it.
+pub fn validate_payload(data: &[u8]) -> color_eyre::Result<()> {
+  if data.is_empty() {
+    bail!("save data is empty");
+  }
+
+  if u32::try_from(data.len()).is_err() {
+    bail!(
+      "save data is too large, expected at most {} bytes but got {}",
+      u32::MAX,
+      data.len()
+    );
+  }
+
+  Ok(())
+}
+
+/// Checks that the payload looks like decrypted HFW checkpoint data.
+///
+/// The decrypted checkpoint data has no documented header we could positively match, so the structural
+/// check can only reject files we know are not checkpoint data (PC saves, SFO files, images)
+/// and the encrypted save images exported by the PS4, while the entropy check
+/// catches encrypted payloads that were renamed to `checkpoint.dat`.
+pub fn check_payload<P: AsRef<Path>>(path: P, data: &[u8]) -> color_eyre::Result<()> {
+  let path = path.as_ref();
+
+  if path
+    .file_name()
+    .and_then(|name| name.to_str())
+    .is_some_and(|name| name.starts_with(ENCRYPTED_IMAGE_PREFIX))
+  {
+    return Err(KnownError::EncryptedSave.report(format!(
+      "save file {:?} is an encrypted PS4 save image, decrypt it before converting",
+      path
+    )));
+  }
+
+  if data.starts_with(GGDS_MAGIC) {
+    bail!("save file {:?} is already a PC save file", path);
+  } else if data.starts_with(SFO_MAGIC) {
+    return Err(KnownError::WrongFolder.report(format!(
+      "save file {:?} is a param.sfo file, not the save data",
+      path
+    )));
+  } else if data.starts_with(PNG_MAGIC) {
+    return Err(KnownError::WrongFolder.report(format!(
+      "save file {:?} is a PNG image, not the save data",
+      path
+    )));
+  }
+
+  debug!("Measuring save data entropy..");
+  let stats = entropy::measure(data);
+  trace!(entropy = ?&stats);
+
+  if stats.looks_encrypted() {
+    return Err(KnownError::EncryptedSave.report(format!(
+      "save file {:?} looks encrypted ({:.3} bits per byte on average), decrypt it before converting",
+      path, stats.average
+    )));
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn known_error(result: color_eyre::Result<()>) -> Option<KnownError> {
+    KnownError::find(&result.unwrap_err())
+  }
+
+  #[test]
+  fn encrypted_save_image_is_rejected() {
+    let data = b"HFW checkpoint test data. ".repeat(1000);
+    assert_eq!(
+      known_error(check_payload("AUTOSAVE0/sdimg_AUTOSAVE0", &data)),
+      Some(KnownError::EncryptedSave)
+    );
+    assert!(check_payload("AUTOSAVE0/checkpoint.dat", &data).is_ok());
+  }
+
+  #[test]
+  fn known_file_formats_are_rejected() {
+    assert!(check_payload("checkpoint.dat", b"GGDS\x01\0\0\0").is_err());
+    assert_eq!(
+      known_error(check_payload("checkpoint.dat", b"\0PSF\x01\x01\0\0")),
+      Some(KnownError::WrongFolder)
+    );
+    assert_eq!(
+      known_error(check_payload("checkpoint.dat", b"\x89PNG\r\n\x1a\n")),
+      Some(KnownError::WrongFolder)
+    );
+  }
+}
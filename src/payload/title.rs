@@ -0,0 +1,80 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use crate::save::{SaveTitle, MAX_TITLE_LENGTH};
+
+/// Shortest text that can hold a save title, e.g. `A - Level 1 - 00:00:00`.
+static MIN_TITLE_LENGTH: usize = 22;
+/// Part of every save title, used to skip the text that can't be one without parsing it.
+static TITLE_MARKER: &str = " - Level ";
+
+/// Looks for the save title in the payload, returning the first text that parses as one.
+///
+/// The layout of the decrypted checkpoint data is not documented, so this is a heuristic:
+/// it only finds the title when the game stores it as a plain ASCII or UTF-8 string.
+pub fn find_title(data: &[u8]) -> Option<SaveTitle> {
+  data
+    .split(|byte| *byte < 0x20 || *byte == 0x7F)
+    .filter(|text| (MIN_TITLE_LENGTH..=MAX_TITLE_LENGTH).contains(&text.len()))
+    .filter_map(|text| std::str::from_utf8(text).ok())
+    .filter(|text| text.contains(TITLE_MARKER))
+    .filter_map(|text| text.parse::<SaveTitle>().ok())
+    .find(|title| !title.quest.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::*;
+
+  #[test]
+  fn title_is_found_between_binary_data() {
+    let mut data = vec![0x00, 0x13, 0x37, 0xff, 0x10];
+    data.extend_from_slice(b"The Point of the Spear - Level 12 - 05:06:07");
+    data.extend_from_slice(&[0x00, 0x42, 0x00]);
+
+    assert_eq!(
+      find_title(&data),
+      Some(SaveTitle {
+        quest: "The Point of the Spear".to_owned(),
+        level: 12,
+        playtime: Duration::from_secs(5 * 3600 + 6 * 60 + 7),
+      })
+    );
+  }
+
+  #[test]
+  fn first_title_is_used() {
+    let data = b"\0Reach For The Stars - Level 1 - 00:01:00\0Death's Door - Level 30 - 40:00:00\0";
+    assert_eq!(find_title(data).unwrap().quest, "Reach For The Stars");
+  }
+
+  #[test]
+  fn text_that_is_not_a_title_is_skipped() {
+    for data in [
+      &b"HFW checkpoint test data. "[..],
+      b"\0 - Level 1 - 00:00:00\0",
+      b"\0Quest - Level one - 00:00:00\0",
+      b"\0Quest - Level 1 - 00:00:00 and more\0",
+      b"\0Quest - Level 1 - \xff0:00:00\0",
+      b"",
+    ] {
+      assert_eq!(find_title(data), None, "{:?}", data);
+    }
+  }
+}
@@ -0,0 +1,111 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use lazy_static::lazy_static;
+use tracing::info;
+
+use crate::log::is_interactive;
+
+lazy_static! {
+  static ref PROGRESS_BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+}
+
+/// Runs the function with the progress bar hidden, so it can write to the terminal.
+pub fn suspend<F: FnOnce() -> R, R>(f: F) -> R {
+  let progress_bar = PROGRESS_BAR.lock().unwrap().clone();
+
+  match progress_bar {
+    Some(progress_bar) => progress_bar.suspend(f),
+    None => f(),
+  }
+}
+
+/// Tracks the progress of a batch of items, shown as a progress bar on terminals or logged for each item otherwise.
+pub struct Progress {
+  total: usize,
+  done: AtomicUsize,
+  started: Instant,
+}
+
+impl Progress {
+  pub fn new(total: usize) -> Self {
+    if is_interactive() {
+      let progress_bar = ProgressBar::new(total as u64).with_style(
+        ProgressStyle::with_template(
+          "{spinner} [{elapsed_precise}] {wide_bar} {pos}/{len} ETA {eta} {msg}",
+        )
+        .expect("progress bar template should be valid"),
+      );
+      progress_bar.enable_steady_tick(Duration::from_millis(100));
+      *PROGRESS_BAR.lock().unwrap() = Some(progress_bar);
+    }
+
+    Self {
+      total,
+      done: AtomicUsize::new(0),
+      started: Instant::now(),
+    }
+  }
+
+  /// Shows the item that is currently being processed.
+  pub fn start(&self, item: &str) {
+    if let Some(progress_bar) = &*PROGRESS_BAR.lock().unwrap() {
+      progress_bar.set_message(item.to_owned());
+    }
+  }
+
+  /// Marks the item as done, logging the progress when the progress bar can't be shown.
+  pub fn inc(&self, item: &str) {
+    let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+    if let Some(progress_bar) = &*PROGRESS_BAR.lock().unwrap() {
+      progress_bar.inc(1);
+      return;
+    }
+
+    let remaining = self
+      .started
+      .elapsed()
+      .mul_f64((self.total - done) as f64 / done as f64);
+    info!(
+      "Processed {} ({} of {}), about {}s remaining..",
+      item,
+      done,
+      self.total,
+      remaining.as_secs()
+    );
+  }
+}
+
+impl Drop for Progress {
+  fn drop(&mut self) {
+    if let Some(progress_bar) = PROGRESS_BAR.lock().unwrap().take() {
+      progress_bar.finish_and_clear();
+    }
+
+    info!(
+      "Processed {} of {} saves in {:.2}s",
+      self.done.load(Ordering::Relaxed),
+      self.total,
+      self.started.elapsed().as_secs_f64()
+    );
+  }
+}
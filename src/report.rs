@@ -0,0 +1,241 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::ValueEnum;
+use color_eyre::eyre::WrapErr;
+use color_eyre::Report;
+use serde::Serialize;
+
+use crate::utils::error::{KnownError, ParseContext};
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ReportFormat {
+  Json,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ConversionReport {
+  pub input: PathBuf,
+  pub save_file: Option<PathBuf>,
+  pub sce_sys: Option<SceSysReport>,
+  pub metadata: Option<MetadataReport>,
+  pub image: Option<ImageReport>,
+  pub output: Option<OutputReport>,
+  pub warnings: Vec<String>,
+  pub status: ConversionStatus,
+  pub error: Option<ErrorReport>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ErrorReport {
+  pub message: String,
+  pub kind: Option<KnownError>,
+  pub suggestion: Option<&'static str>,
+  pub note: Option<&'static str>,
+  pub parse_context: Option<ParseContext>,
+}
+
+impl ErrorReport {
+  pub fn new(message: String, kind: Option<KnownError>) -> Self {
+    Self {
+      message,
+      kind,
+      suggestion: kind.map(KnownError::suggestion),
+      note: kind.map(KnownError::note),
+      parse_context: None,
+    }
+  }
+}
+
+impl From<&Report> for ErrorReport {
+  fn from(report: &Report) -> Self {
+    Self {
+      parse_context: ParseContext::find(report).cloned(),
+      ..Self::new(format!("{:#}", report), KnownError::find(report))
+    }
+  }
+}
+
+#[derive(Serialize, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionStatus {
+  Converted,
+  Skipped,
+  #[default]
+  Failed,
+}
+
+#[derive(Serialize, Debug)]
+pub struct SceSysReport {
+  pub path: PathBuf,
+  pub param_sfo: Option<PathBuf>,
+  pub icon: Option<PathBuf>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MetadataReport {
+  pub file_name: ReportField<String>,
+  pub title: ReportField<String>,
+  pub sub_title: ReportField<String>,
+  pub checksum: ReportField<u32>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReportField<T> {
+  pub value: T,
+  pub source: MetadataSource,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataSource {
+  Sfo,
+  SaveDir,
+  Payload,
+  Default,
+  Cli,
+}
+
+#[derive(Serialize, Debug)]
+pub struct ImageReport {
+  pub source: ImageSource,
+  pub path: Option<PathBuf>,
+  pub raw: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageSource {
+  Sfo,
+  Cli,
+  Placeholder,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OutputReport {
+  pub path: PathBuf,
+  pub bytes_written: usize,
+  pub payload_sha256: String,
+  pub output_sha256: String,
+}
+
+impl<T> ReportField<T> {
+  pub fn new(value: T, source: MetadataSource) -> Self {
+    Self { value, source }
+  }
+}
+
+/// Writes the reports to the given file, or to the standard output if none was given.
+/// The report of a single save is written as is, while the reports of an archive are always written as a list,
+/// even when it contains a single save (or failed to be read), so the schema only depends on the input kind.
+pub fn write_reports<P: AsRef<Path>>(
+  reports: &[ConversionReport],
+  is_archive: bool,
+  format: ReportFormat,
+  path: Option<P>,
+) -> color_eyre::Result<()> {
+  let content = match (format, reports) {
+    (ReportFormat::Json, [report]) if !is_archive => serde_json::to_string_pretty(report),
+    (ReportFormat::Json, reports) => serde_json::to_string_pretty(reports),
+  }
+  .wrap_err("failed to serialize the report")?;
+
+  match path {
+    Some(path) => fs::write(path.as_ref(), content + "\n")
+      .wrap_err_with(|| format!("failed to write the report to {:?}", path.as_ref())),
+    None => {
+      println!("{}", content);
+      Ok(())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use serde_json::{json, Value};
+
+  use super::*;
+
+  fn read_json(path: &Path) -> Value {
+    serde_json::from_slice(&fs::read(path).unwrap()).unwrap()
+  }
+
+  #[test]
+  fn error_report_carries_the_known_error() {
+    let report = KnownError::WrongFolder.report("no save file found");
+    let value = serde_json::to_value(ErrorReport::from(&report)).unwrap();
+
+    assert_eq!(value["message"], "no save file found");
+    assert_eq!(value["kind"], "wrong_folder");
+    assert_eq!(value["suggestion"], KnownError::WrongFolder.suggestion());
+    assert_eq!(value["note"], KnownError::WrongFolder.note());
+    assert_eq!(value["parse_context"], Value::Null);
+  }
+
+  #[test]
+  fn conversion_report_describes_the_sources() {
+    let report = ConversionReport {
+      input: PathBuf::from("AUTOSAVE0"),
+      metadata: Some(MetadataReport {
+        file_name: ReportField::new("autosave0".to_owned(), MetadataSource::SaveDir),
+        title: ReportField::new("Title".to_owned(), MetadataSource::Default),
+        sub_title: ReportField::new("Autosave".to_owned(), MetadataSource::Cli),
+        checksum: ReportField::new(1, MetadataSource::Sfo),
+      }),
+      image: Some(ImageReport {
+        source: ImageSource::Placeholder,
+        path: None,
+        raw: false,
+      }),
+      status: ConversionStatus::Converted,
+      ..ConversionReport::default()
+    };
+    let value = serde_json::to_value(&report).unwrap();
+
+    assert_eq!(value["input"], "AUTOSAVE0");
+    assert_eq!(value["status"], "converted");
+    assert_eq!(
+      value["metadata"]["file_name"],
+      json!({ "value": "autosave0", "source": "save_dir" })
+    );
+    assert_eq!(value["metadata"]["sub_title"]["source"], "cli");
+    assert_eq!(value["image"]["source"], "placeholder");
+    assert_eq!(value["error"], Value::Null);
+    assert_eq!(value["warnings"], json!([]));
+  }
+
+  #[test]
+  fn archive_reports_are_always_a_list() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report.json");
+    let reports = [ConversionReport::default()];
+
+    write_reports(&reports, false, ReportFormat::Json, Some(&path)).unwrap();
+    assert!(read_json(&path).is_object());
+
+    write_reports(&reports, true, ReportFormat::Json, Some(&path)).unwrap();
+    let value = read_json(&path);
+    assert_eq!(value.as_array().map(Vec::len), Some(1));
+    assert_eq!(value[0]["status"], "failed");
+
+    write_reports(&[], true, ReportFormat::Json, Some(&path)).unwrap();
+    assert_eq!(read_json(&path), json!([]));
+  }
+}
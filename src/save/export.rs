@@ -0,0 +1,100 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::eyre::{eyre, WrapErr};
+use tracing::debug;
+
+use crate::save::{SaveFile, SaveMetadata};
+use crate::sfo::{write_sfo, SFOParamData};
+use crate::utils::error::KnownError;
+
+/// Files of the PS4 save directory written from a PC save file.
+#[derive(Debug)]
+pub struct ExportedSave {
+  pub save_file: PathBuf,
+  pub param_sfo: PathBuf,
+  pub icon: Option<PathBuf>,
+}
+
+impl SaveFile {
+  /// Resolves the save metadata stored in the PC save file, the slot name is taken from the file name.
+  pub fn metadata(&self) -> color_eyre::Result<SaveMetadata> {
+    let file_name = self
+      .path
+      .file_stem()
+      .and_then(|name| name.to_str())
+      .ok_or_else(|| eyre!("failed to resolve save slot from {:?} path", &self.path))?;
+
+    Ok(SaveMetadata::new(
+      file_name.to_lowercase(),
+      self.title.to_string(),
+      self.sub_title.to_string(),
+      self.checksum,
+    ))
+  }
+
+  /// Writes the save as a PS4 save directory named after the save slot,
+  /// containing the save data, the `param.sfo` file and the save image as the icon.
+  pub fn export_ps4<P: AsRef<Path>>(
+    &self,
+    output_dir: P,
+    overwrite: bool,
+  ) -> color_eyre::Result<ExportedSave> {
+    let metadata = self.metadata()?;
+    let dir = output_dir.as_ref().join(metadata.file_name.to_uppercase());
+    let sce_sys = dir.join("sce_sys");
+    let save_file = dir.join("checkpoint.dat");
+    let param_sfo = sce_sys.join("param.sfo");
+
+    if save_file.exists() && !overwrite {
+      return Err(
+        KnownError::OutputExists.report(format!("PS4 save file {:?} already exists", &save_file)),
+      );
+    }
+
+    fs::create_dir_all(&sce_sys)
+      .wrap_err_with(|| format!("failed to create PS4 save directory {:?}", &sce_sys))?;
+
+    debug!("Writing PS4 save data..");
+    fs::write(&save_file, &self.data)
+      .wrap_err_with(|| format!("failed to write save data {:?}", &save_file))?;
+
+    debug!("Writing param.sfo file..");
+    let params = BTreeMap::<String, SFOParamData>::from(&metadata);
+    fs::write(&param_sfo, write_sfo(&params)?)
+      .wrap_err_with(|| format!("failed to write param.sfo file {:?}", &param_sfo))?;
+
+    let icon = if self.image.is_empty() {
+      None
+    } else {
+      let icon = sce_sys.join("icon0.png");
+      fs::write(&icon, &self.image)
+        .wrap_err_with(|| format!("failed to write save image {:?}", &icon))?;
+      Some(icon)
+    };
+
+    Ok(ExportedSave {
+      save_file,
+      param_sfo,
+      icon,
+    })
+  }
+}
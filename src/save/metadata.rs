@@ -15,7 +15,15 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::path::PathBuf;
+use std::path::Path;
+
+/// Prefixes of the save slot directories, with the subtitle the game gives to the slot.
+/// Only the autosave subtitle was seen in the PS4 saves, the other ones are left empty.
+static SAVE_SLOTS: [(&str, Option<&str>); 3] = [
+  ("AUTOSAVE", Some("Autosave")),
+  ("MANUALSAVE", None),
+  ("QUICKSAVE", None),
+];
 
 #[derive(Debug)]
 pub struct SaveMetadata {
@@ -23,17 +31,10 @@ pub struct SaveMetadata {
   pub title: String,
   pub sub_title: String,
   pub checksum: u32,
-  pub image_path: Option<PathBuf>,
 }
 
 impl SaveMetadata {
-  pub fn new<F, T, S>(
-    file_name: F,
-    title: T,
-    sub_title: S,
-    checksum: u32,
-    image_path: Option<PathBuf>,
-  ) -> Self
+  pub fn new<F, T, S>(file_name: F, title: T, sub_title: S, checksum: u32) -> Self
   where
     F: AsRef<str>,
     T: AsRef<str>,
@@ -44,9 +45,29 @@ impl SaveMetadata {
       title: title.as_ref().to_owned(),
       sub_title: sub_title.as_ref().to_owned(),
       checksum,
-      image_path,
     }
   }
+
+  /// Derives the metadata from the name of the directory containing the save file (for example, `AUTOSAVE3`),
+  /// returns `None` when it doesn't look like a save slot.
+  pub fn from_save_dir<P: AsRef<Path>>(save_dir: P) -> Option<Self> {
+    let dir_name = save_dir
+      .as_ref()
+      .file_name()
+      .and_then(|name| name.to_str())
+      .map(|name| name.to_uppercase())?;
+
+    SAVE_SLOTS.iter().find_map(|(prefix, sub_title)| {
+      dir_name
+        .strip_prefix(prefix)
+        .filter(|index| !index.is_empty() && index.chars().all(|char| char.is_ascii_digit()))
+        .map(|_| Self {
+          file_name: dir_name.to_lowercase(),
+          sub_title: sub_title.unwrap_or_default().to_owned(),
+          ..Self::default()
+        })
+    })
+  }
 }
 
 impl Default for SaveMetadata {
@@ -77,7 +98,40 @@ impl Default for SaveMetadata {
       title: "Reach For The Stars - Level 1 - 00:00:00".to_owned(),
       sub_title: "Autosave".to_owned(),
       checksum: 105182377u32, // 0xA9, 0xF4, 0x44, 0x06
-      image_path: None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn autosave_dir() {
+    let metadata = SaveMetadata::from_save_dir("saves/AUTOSAVE3").unwrap();
+    assert_eq!(metadata.file_name, "autosave3");
+    assert_eq!(metadata.sub_title, "Autosave");
+    assert_eq!(metadata.title, SaveMetadata::default().title);
+    assert_eq!(metadata.checksum, SaveMetadata::default().checksum);
+  }
+
+  #[test]
+  fn manual_save_dir() {
+    let metadata = SaveMetadata::from_save_dir("saves/manualsave1").unwrap();
+    assert_eq!(metadata.file_name, "manualsave1");
+    assert_eq!(metadata.sub_title, "");
+  }
+
+  #[test]
+  fn unknown_dir() {
+    for dir in [
+      "saves/checkpoint",
+      "saves/AUTOSAVE",
+      "saves/AUTOSAVE3b",
+      "saves/SAVE3",
+      "",
+    ] {
+      assert!(SaveMetadata::from_save_dir(dir).is_none(), "{:?}", dir);
     }
   }
 }
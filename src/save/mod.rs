@@ -17,23 +17,35 @@
 
 use std::fs;
 use std::fs::File;
-use std::io::{BufWriter, ErrorKind};
+use std::io::{BufWriter, Cursor, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 
-use binrw::io::BufReader;
+use binrw::helpers::until_eof;
 use binrw::{binrw, BinRead, BinWrite, NullString};
 use color_eyre::eyre::{bail, WrapErr};
 use color_eyre::Report;
 use derivative::Derivative;
-use path_absolutize::Absolutize;
-use tracing::{error, info, warn};
+use tracing::{debug, debug_span, error, warn};
 
 pub use metadata::SaveMetadata;
+pub use recover::SalvagedSave;
+pub use title::{format_playtime, parse_playtime, SaveTitle, MAX_TITLE_LENGTH};
 
+use crate::payload::{check_payload, validate_payload};
+use crate::thumbnail;
 use crate::utils;
-use crate::utils::error::ToReport;
+use crate::utils::error::{parse_error_report, KnownError, ToReport};
 
+mod export;
 mod metadata;
+mod recover;
+mod title;
+
+pub static GGDS_MAGIC: &[u8] = b"GGDS";
+/// The only save file version we know how to produce.
+pub static SUPPORTED_VERSION: u32 = 1;
+static TITLE_CAPACITY: usize = 0x80;
+static SUB_TITLE_CAPACITY: usize = 0x100;
 
 #[binrw]
 #[derive(Derivative)]
@@ -43,107 +55,352 @@ pub struct SaveFile {
   #[brw(ignore)]
   path: PathBuf,
 
-  version: u32,
-  checksum: u32,
+  pub version: u32,
+  /// Value of the `SAVEDATA_LIST_PARAM` param from the PS4 `param.sfo` file,
+  /// the game uses it to identify the save in the save list, it is not derived from the save data.
+  pub checksum: u32,
   #[bw(calc = data.len() as u32)]
   data_length: u32,
   #[bw(calc = image.len() as u32)]
   image_length: u32,
-  #[brw(pad_size_to = 0x80)]
-  title: NullString,
-  #[brw(pad_size_to = 0x100)]
-  sub_title: NullString,
+  pub title: NullString,
+  /// Bytes after the title terminator, kept so that unmodified files are written back byte for byte.
+  #[br(count = TITLE_CAPACITY.saturating_sub(title.len() + 1))]
+  #[bw(map = |padding: &Vec<u8>| fit_padding(padding, title.len() + 1, TITLE_CAPACITY))]
+  #[derivative(Debug = "ignore")]
+  title_padding: Vec<u8>,
+  pub sub_title: NullString,
+  #[br(count = SUB_TITLE_CAPACITY.saturating_sub(sub_title.len() + 1))]
+  #[bw(map = |padding: &Vec<u8>| fit_padding(padding, sub_title.len() + 1, SUB_TITLE_CAPACITY))]
+  #[derivative(Debug = "ignore")]
+  sub_title_padding: Vec<u8>,
   #[br(count = data_length)]
   #[derivative(Debug(format_with = "crate::utils::fmt::debug_vec"))]
-  data: Vec<u8>,
+  pub data: Vec<u8>,
   #[br(count = image_length)]
   #[derivative(Debug(format_with = "crate::utils::fmt::debug_vec"))]
-  image: Vec<u8>,
+  pub image: Vec<u8>,
+  /// Data found after the image, the game doesn't write any but it is kept to not lose anything.
+  #[br(parse_with = until_eof)]
+  #[derivative(Debug(format_with = "crate::utils::fmt::debug_vec"))]
+  pub trailing: Vec<u8>,
+}
+
+/// Reuses the original padding of a fixed size field if the value still has the same length, zeroes otherwise.
+fn fit_padding(padding: &[u8], used: usize, capacity: usize) -> Vec<u8> {
+  let length = capacity.saturating_sub(used);
+
+  if padding.len() == length {
+    padding.to_vec()
+  } else {
+    vec![0; length]
+  }
+}
+
+#[derive(Debug)]
+pub struct GeneratedSave {
+  pub path: PathBuf,
+  pub size: usize,
+  pub sha256: String,
+  pub payload_sha256: String,
+  pub placeholder_image: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct GenerateOptions {
+  pub skip_payload_check: bool,
 }
 
 impl SaveFile {
   //noinspection DuplicatedCode
   pub fn open<P: AsRef<Path>>(path: P) -> color_eyre::Result<Self> {
+    let data = fs::read(path.as_ref())?;
     let mut this =
-      Self::read(&mut BufReader::new(File::open(path.as_ref())?)).map_err(|err| err.to_report())?;
+      Self::read(&mut Cursor::new(&data)).map_err(|err| parse_error_report(&err, &data))?;
     this.path = path.as_ref().to_path_buf();
 
+    if this.version != SUPPORTED_VERSION {
+      warn!(
+        "Save file has an unknown version {}, only version {} is supported.",
+        this.version, SUPPORTED_VERSION
+      );
+    }
+    // The checksum is the `SAVEDATA_LIST_PARAM` param of the PS4 save the file was converted from.
+    // It isn't computed from the save data, so without the PS4 save we can only tell when it's missing.
+    if this.checksum == 0 {
+      warn!(
+        "Save file has no checksum, it should hold the SAVEDATA_LIST_PARAM param of the PS4 save. Use verify --source <PS4_SAVE> --fix-checksum to restore it."
+      );
+    }
+    if !this.trailing.is_empty() {
+      warn!(
+        "Save file has {} bytes of unknown data after the image, they will be kept as is.",
+        this.trailing.len()
+      );
+    }
+
     Ok(this)
   }
 
-  pub fn generate<I, O>(
-    save_file: I,
-    output_dir: O,
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Makes sure the title and subtitle fit in their fixed size fields (including the null terminator).
+  fn validate(&self) -> color_eyre::Result<()> {
+    if self.title.len() >= TITLE_CAPACITY {
+      bail!(
+        "save title is too long, expected at most {} bytes but got {}",
+        TITLE_CAPACITY - 1,
+        self.title.len()
+      );
+    }
+
+    if self.sub_title.len() >= SUB_TITLE_CAPACITY {
+      bail!(
+        "save subtitle is too long, expected at most {} bytes but got {}",
+        SUB_TITLE_CAPACITY - 1,
+        self.sub_title.len()
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Writes the save file to the given path, replacing it only once the whole file was written.
+  /// Save files with a version we don't know how to produce are only written if explicitly allowed.
+  pub fn write_to<P: AsRef<Path>>(
+    &self,
+    path: P,
+    allow_unknown_version: bool,
+  ) -> color_eyre::Result<()> {
+    let path = path.as_ref();
+
+    self.validate()?;
+    if self.version != SUPPORTED_VERSION {
+      if !allow_unknown_version {
+        bail!(
+          "refusing to write save file version {}, only version {} is supported. Use --allow-unknown-version to write it anyway",
+          self.version,
+          SUPPORTED_VERSION
+        );
+      }
+
+      warn!(
+        "Writing save file with an unknown version {}, the game may refuse to load it.",
+        self.version
+      );
+    }
+
+    write_atomically(path, true, |writer| {
+      self.write(writer).map_err(|err| err.to_report())
+    })?;
+
+    Ok(())
+  }
+
+  /// Builds the PC save file in memory from the PS4 save data and the already processed image,
+  /// the placeholder image is generated if there is none. The `source` path is only used to check the save data.
+  /// Returns the save file named after the save slot and whether the placeholder image was used.
+  pub fn build(
+    source: &Path,
+    save_data: Vec<u8>,
+    image: Option<Vec<u8>>,
     metadata: SaveMetadata,
-    overwrite: bool,
-  ) -> color_eyre::Result<()>
-  where
-    I: AsRef<Path>,
-    O: AsRef<Path>,
-  {
-    let output_dir = output_dir
-      .as_ref()
-      .absolutize()
-      .wrap_err("failed to resolve output directory path")?;
-    let save_data = fs::read(&save_file)
-      .wrap_err_with(|| format!("failed to read save data {:?}", save_file.as_ref()))?;
-    let image_data = if let Some(image_path) = &metadata.image_path {
-      if !image_path.exists() || !image_path.is_file() {
-        warn!("No image found for the save file, generating blank image..");
-        utils::generate_blank_image()?
+    options: &GenerateOptions,
+  ) -> color_eyre::Result<(Self, bool)> {
+    {
+      let _span = debug_span!("check_payload", path = ?source).entered();
+      validate_payload(&save_data).wrap_err_with(|| format!("invalid save data {:?}", source))?;
+
+      if options.skip_payload_check {
+        warn!("Skipping the save data check, the generated save file may not work.");
       } else {
-        fs::read(&image_path)
-          .wrap_err_with(|| format!("failed to read save image {:?}", &image_path))?
+        check_payload(source, &save_data)?;
       }
-    } else {
-      warn!("No image found for the save file, generating blank image..");
-      utils::generate_blank_image()?
-    };
-
-    if !output_dir.exists() {
-      fs::create_dir_all(&output_dir)
-        .wrap_err_with(|| format!("failed to create output directory {:?}", &output_dir))?;
-    } else if !output_dir.is_dir() {
-      bail!("output path is not a directory");
     }
 
-    let output_file_path = output_dir.join(&metadata.file_name).with_extension("dat");
-    let mut writer = BufWriter::new(if overwrite {
-      File::create(&output_file_path)
-        .wrap_err_with(|| format!("failed to create file {:?}", &output_file_path))?
-    } else {
-      match File::create_new(&output_file_path) {
-        Ok(writer) => writer,
-        Err(err) => {
-          return match err {
-            _ if err.kind() == ErrorKind::AlreadyExists => {
-              error!(
-                "Unable to generate the save file, file already exists. Use --force to overwrite it."
-              );
-              Ok(())
-            }
-            _ => Err(
-              Report::from(err).wrap_err(format!("failed to create file {:?}", &output_file_path)),
-            ),
-          }
-        }
+    let (image_data, placeholder_image) = match image {
+      Some(image) => (image, false),
+      None => {
+        warn!("No image found for the save file, generating placeholder image..");
+        (
+          thumbnail::generate_placeholder_image(&metadata.title, &metadata.sub_title)?,
+          true,
+        )
       }
-    });
+    };
+
     let save = SaveFile {
-      path: output_file_path,
-      version: 1,
+      path: PathBuf::from(&metadata.file_name).with_extension("dat"),
+      version: SUPPORTED_VERSION,
       checksum: metadata.checksum,
       title: metadata.title.into(),
+      title_padding: vec![],
       sub_title: metadata.sub_title.into(),
+      sub_title_padding: vec![],
       data: save_data,
       image: image_data,
+      trailing: vec![],
     };
+    save.validate()?;
 
-    save
-      .write(&mut writer)
-      .map_err(|err| err.to_report()) // TODO: Add error context
-      .inspect(|_| {
-        info!("Saved the generated save file to {:?}", &save.path);
-      })
+    Ok((save, placeholder_image))
+  }
+
+  /// Serializes the save file into the bytes of the GGDS format.
+  pub fn to_bytes(&self) -> color_eyre::Result<Vec<u8>> {
+    self.validate()?;
+
+    let mut output = vec![];
+    self
+      .write(&mut Cursor::new(&mut output))
+      .map_err(|err| err.to_report())
+      .wrap_err("failed to serialize the save file")?;
+
+    Ok(output)
+  }
+}
+
+impl GeneratedSave {
+  pub fn new(path: PathBuf, output: &[u8], save: &SaveFile, placeholder_image: bool) -> Self {
+    Self {
+      path,
+      size: output.len(),
+      sha256: utils::sha256_hex(output),
+      payload_sha256: utils::sha256_hex(&save.data),
+      placeholder_image,
+    }
+  }
+}
+
+/// Writes the generated file, unless it already exists and shouldn't be overwritten in which case `false` is returned.
+pub fn write_output(path: &Path, data: &[u8], overwrite: bool) -> color_eyre::Result<bool> {
+  let written = write_atomically(path, overwrite, |writer| Ok(writer.write_all(data)?))?;
+  if !written {
+    error!(
+      "Unable to generate the save file, file already exists. {}",
+      KnownError::OutputExists.suggestion()
+    );
+  }
+
+  Ok(written)
+}
+
+/// Keeps up to `count` previous copies of the file before it is overwritten, from `<name>.1.bak` (the newest)
+/// to `<name>.<count>.bak`, the oldest copy is dropped once there are more of them.
+pub fn backup_output(path: &Path, count: usize) -> color_eyre::Result<()> {
+  if count == 0 || !path.is_file() {
+    return Ok(());
+  }
+
+  let backup_path = |index: usize| {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.bak", index));
+    path.with_file_name(name)
+  };
+  for index in (1..count).rev() {
+    let from = backup_path(index);
+    if from.is_file() {
+      fs::rename(&from, backup_path(index + 1))
+        .wrap_err_with(|| format!("failed to rotate backup {:?}", &from))?;
+    }
+  }
+
+  let backup = backup_path(1);
+  fs::copy(path, &backup).wrap_err_with(|| format!("failed to back up {:?}", path))?;
+  debug!("Backed up {:?} to {:?}", path, &backup);
+
+  Ok(())
+}
+
+/// Writes the file through a temporary file next to it, which is synced and only then renamed over the path,
+/// so an interrupted write never leaves a partially written file behind.
+/// Without `overwrite` an existing file is kept as is, and `false` is returned.
+fn write_atomically<F>(path: &Path, overwrite: bool, write: F) -> color_eyre::Result<bool>
+where
+  F: FnOnce(&mut BufWriter<&mut File>) -> color_eyre::Result<()>,
+{
+  // The temporary file is removed when anything fails before it is renamed.
+  let dir = match path.parent() {
+    Some(dir) if !dir.as_os_str().is_empty() => dir,
+    _ => Path::new("."),
+  };
+  let existing = fs::metadata(path).ok();
+  let mut builder = tempfile::Builder::new();
+  builder
+    .prefix(path.file_name().unwrap_or_default())
+    .suffix(".tmp");
+  // Temporary files are only accessible by their owner, while a new file should get the usual permissions.
+  #[cfg(unix)]
+  if existing.is_none() {
+    use std::os::unix::fs::PermissionsExt;
+    builder.permissions(fs::Permissions::from_mode(0o666));
+  }
+  let mut tmp_file = builder
+    .tempfile_in(dir)
+    .wrap_err_with(|| format!("failed to create a temporary file in {:?}", dir))?;
+  // The replaced file keeps its permissions.
+  if let Some(existing) = existing {
+    tmp_file
+      .as_file()
+      .set_permissions(existing.permissions())
+      .wrap_err_with(|| format!("failed to copy the permissions of {:?}", path))?;
+  }
+
+  let mut writer = BufWriter::new(tmp_file.as_file_mut());
+  write(&mut writer)
+    .and_then(|_| writer.flush().map_err(Report::from))
+    .wrap_err_with(|| format!("failed to write file {:?}", path))?;
+  drop(writer);
+  tmp_file
+    .as_file()
+    .sync_all()
+    .wrap_err_with(|| format!("failed to write file {:?}", path))?;
+
+  let persisted = if overwrite {
+    tmp_file.persist(path)
+  } else {
+    tmp_file.persist_noclobber(path)
+  };
+  match persisted {
+    Ok(_) => Ok(true),
+    Err(err) if !overwrite && err.error.kind() == ErrorKind::AlreadyExists => Ok(false),
+    Err(err) => Err(Report::from(err.error).wrap_err(format!("failed to replace file {:?}", path))),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use color_eyre::eyre::eyre;
+
+  use super::*;
+
+  #[test]
+  fn write_output_keeps_existing_files_without_overwrite() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("autosave0.dat");
+    fs::write(&path, b"existing").unwrap();
+
+    assert!(!write_output(&path, b"generated", false).unwrap());
+    assert_eq!(fs::read(&path).unwrap(), b"existing");
+    assert!(write_output(&path, b"generated", true).unwrap());
+    assert_eq!(fs::read(&path).unwrap(), b"generated");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn failed_writes_keep_the_existing_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("autosave0.dat");
+    fs::write(&path, b"existing").unwrap();
+
+    let result = write_atomically(&path, true, |writer| {
+      writer.write_all(b"partial")?;
+      Err(eyre!("interrupted"))
+    });
+    assert!(result.is_err());
+    assert_eq!(fs::read(&path).unwrap(), b"existing");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
   }
 }
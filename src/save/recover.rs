@@ -0,0 +1,323 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::path::PathBuf;
+
+use binrw::NullString;
+use color_eyre::eyre::{bail, WrapErr};
+use derivative::Derivative;
+use tracing::warn;
+
+use crate::payload::validate_payload;
+use crate::save::{SaveFile, GGDS_MAGIC, SUB_TITLE_CAPACITY, TITLE_CAPACITY};
+use crate::thumbnail;
+use crate::utils;
+
+static VERSION_OFFSET: usize = 0x04;
+static CHECKSUM_OFFSET: usize = 0x08;
+static DATA_LENGTH_OFFSET: usize = 0x0c;
+static IMAGE_LENGTH_OFFSET: usize = 0x10;
+static TITLE_OFFSET: usize = 0x14;
+static SUB_TITLE_OFFSET: usize = TITLE_OFFSET + TITLE_CAPACITY;
+static DATA_OFFSET: usize = SUB_TITLE_OFFSET + SUB_TITLE_CAPACITY;
+
+/// Everything that could be read from a truncated or damaged PC save file.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct SalvagedSave {
+  pub file_size: usize,
+  pub version: Option<u32>,
+  pub checksum: Option<u32>,
+  pub data_length: Option<u32>,
+  pub image_length: Option<u32>,
+  pub title: Option<String>,
+  pub sub_title: Option<String>,
+  /// Save data up to the available length.
+  #[derivative(Debug(format_with = "crate::utils::fmt::debug_vec"))]
+  pub data: Vec<u8>,
+  /// Save image, only present if it is complete and can be decoded.
+  #[derivative(Debug(format_with = "crate::utils::fmt::debug_option_vec"))]
+  pub image: Option<Vec<u8>>,
+  /// Descriptions of the parts of the file that are missing or damaged.
+  pub missing: Vec<String>,
+}
+
+impl SalvagedSave {
+  /// Reads the fields of the save file one by one, stopping at the first one that isn't available,
+  /// unlike [`SaveFile::open`] which fails if any part of the file is missing.
+  pub fn read(bytes: &[u8]) -> color_eyre::Result<Self> {
+    if !bytes.starts_with(GGDS_MAGIC) {
+      bail!("file is not a PC save file, missing {:?} magic", "GGDS");
+    }
+
+    let mut missing = vec![];
+    let mut read_u32 = |name: &str, offset: usize| {
+      let value = bytes
+        .get(offset..offset + 4)
+        .map(|value| u32::from_le_bytes(value.try_into().unwrap()));
+      if value.is_none() {
+        missing.push(format!("{} at 0x{:x}", name, offset));
+      }
+
+      value
+    };
+    let version = read_u32("version", VERSION_OFFSET);
+    let checksum = read_u32("checksum", CHECKSUM_OFFSET);
+    let data_length = read_u32("save data length", DATA_LENGTH_OFFSET);
+    let image_length = read_u32("image length", IMAGE_LENGTH_OFFSET);
+
+    let mut read_string = |name: &str, offset: usize, capacity: usize| {
+      let value = bytes.get(offset..offset + capacity).map(|value| {
+        let end = value
+          .iter()
+          .position(|&byte| byte == 0)
+          .unwrap_or(value.len());
+        String::from_utf8_lossy(&value[..end]).into_owned()
+      });
+      if value.is_none() {
+        missing.push(format!("{} at 0x{:x}", name, offset));
+      }
+
+      value
+    };
+    let title = read_string("title", TITLE_OFFSET, TITLE_CAPACITY);
+    let sub_title = read_string("subtitle", SUB_TITLE_OFFSET, SUB_TITLE_CAPACITY);
+
+    let available = bytes.get(DATA_OFFSET..).unwrap_or_default();
+    let data = match data_length {
+      Some(length) => {
+        let length = length as usize;
+        if available.len() < length {
+          missing.push(format!(
+            "save data, {} of {} bytes available",
+            available.len(),
+            length
+          ));
+        }
+
+        available[..length.min(available.len())].to_vec()
+      }
+      None => vec![],
+    };
+
+    let image = match (data_length, image_length) {
+      (Some(data_length), Some(image_length)) => {
+        let available = available.get(data_length as usize..).unwrap_or_default();
+        let length = image_length as usize;
+
+        if available.len() < length {
+          missing.push(format!(
+            "image, {} of {} bytes available",
+            available.len(),
+            length
+          ));
+          None
+        } else if length == 0 {
+          Some(vec![])
+        } else {
+          let image = &available[..length];
+          match utils::image_dimensions(image) {
+            Ok(_) => Some(image.to_vec()),
+            Err(err) => {
+              missing.push(format!("image, {:#}", err));
+              None
+            }
+          }
+        }
+      }
+      _ => None,
+    };
+
+    Ok(Self {
+      file_size: bytes.len(),
+      version,
+      checksum,
+      data_length,
+      image_length,
+      title,
+      sub_title,
+      data,
+      image,
+      missing,
+    })
+  }
+
+  pub fn is_complete(&self) -> bool {
+    self.missing.is_empty()
+  }
+
+  pub fn is_data_complete(&self) -> bool {
+    self
+      .data_length
+      .is_some_and(|length| self.data.len() == length as usize)
+  }
+
+  /// Rebuilds a consistent save file from the salvaged parts, replacing a missing image with a placeholder.
+  pub fn rebuild<P: Into<PathBuf>>(&self, path: P) -> color_eyre::Result<SaveFile> {
+    let (Some(version), Some(checksum), Some(title), Some(sub_title)) =
+      (self.version, self.checksum, &self.title, &self.sub_title)
+    else {
+      bail!("the save file header is incomplete, only the save data can be extracted");
+    };
+    validate_payload(&self.data).wrap_err("no usable save data left in the save file")?;
+
+    if !self.is_data_complete() {
+      warn!("The save data is truncated, the game may refuse to load the rebuilt save file.");
+    }
+
+    let image = match &self.image {
+      Some(image) => image.clone(),
+      None => {
+        warn!("The save image is missing or damaged, generating placeholder image..");
+        thumbnail::generate_placeholder_image(title, sub_title)?
+      }
+    };
+
+    Ok(SaveFile {
+      path: path.into(),
+      version,
+      checksum,
+      title: NullString::from(title.as_str()),
+      title_padding: vec![],
+      sub_title: NullString::from(sub_title.as_str()),
+      sub_title_padding: vec![],
+      data: self.data.clone(),
+      image,
+      trailing: vec![],
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::Cursor;
+
+  use binrw::BinRead;
+
+  use super::*;
+
+  static TITLE: &str = "Reach For The Stars - Level 1 - 00:00:00";
+  static SUB_TITLE: &str = "Manual";
+
+  /// Builds a complete save file, returning its bytes and the length of the save data.
+  fn save_bytes() -> (Vec<u8>, usize) {
+    let data = b"HFW checkpoint test data. ".repeat(40);
+    let save = SaveFile {
+      path: PathBuf::new(),
+      version: 1,
+      checksum: 424242,
+      title: NullString::from(TITLE),
+      title_padding: vec![],
+      sub_title: NullString::from(SUB_TITLE),
+      sub_title_padding: vec![],
+      data: data.clone(),
+      image: thumbnail::generate_placeholder_image("Original", "Image").unwrap(),
+      trailing: vec![],
+    };
+
+    (save.to_bytes().unwrap(), data.len())
+  }
+
+  #[test]
+  fn complete_save() {
+    let (bytes, data_length) = save_bytes();
+    let salvaged = SalvagedSave::read(&bytes).unwrap();
+    assert!(salvaged.is_complete(), "{:?}", salvaged.missing);
+
+    let rebuilt = salvaged.rebuild("rebuilt.dat").unwrap();
+    assert_eq!(rebuilt.data.len(), data_length);
+    assert_eq!(rebuilt.to_bytes().unwrap(), bytes);
+  }
+
+  #[test]
+  fn cut_in_header() {
+    let (bytes, _) = save_bytes();
+
+    let salvaged = SalvagedSave::read(&bytes[..0x0a]).unwrap();
+    assert_eq!(salvaged.version, Some(1));
+    assert_eq!(salvaged.checksum, None);
+    assert_eq!(salvaged.title, None);
+    assert!(salvaged.data.is_empty());
+    assert!(salvaged.missing[0].starts_with("checksum at 0x8"));
+
+    let salvaged = SalvagedSave::read(&bytes[..TITLE_OFFSET + 0x10]).unwrap();
+    assert_eq!(salvaged.checksum, Some(424242));
+    assert_eq!(salvaged.title, None);
+    assert_eq!(salvaged.missing[..2], ["title at 0x14", "subtitle at 0x94"]);
+    assert!(salvaged.missing[2].starts_with("save data, 0 of "));
+
+    let err = salvaged.rebuild("rebuilt.dat").unwrap_err();
+    assert!(err.to_string().contains("header is incomplete"), "{}", err);
+  }
+
+  #[test]
+  fn cut_in_save_data() {
+    let (bytes, data_length) = save_bytes();
+    let salvaged = SalvagedSave::read(&bytes[..DATA_OFFSET + 100]).unwrap();
+    assert_eq!(salvaged.title.as_deref(), Some(TITLE));
+    assert_eq!(salvaged.data, &bytes[DATA_OFFSET..DATA_OFFSET + 100]);
+    assert!(!salvaged.is_data_complete());
+    assert_eq!(salvaged.image, None);
+    assert_eq!(
+      salvaged.missing[0],
+      format!("save data, 100 of {} bytes available", data_length)
+    );
+    assert!(salvaged.missing[1].starts_with("image, 0 of "));
+
+    // The header is rewritten to describe the truncated data and the placeholder image.
+    let rebuilt = salvaged.rebuild("rebuilt.dat").unwrap();
+    assert_eq!(rebuilt.data.len(), 100);
+    assert_eq!(
+      rebuilt.image,
+      thumbnail::generate_placeholder_image(TITLE, SUB_TITLE).unwrap()
+    );
+
+    let reread = SaveFile::read(&mut Cursor::new(rebuilt.to_bytes().unwrap())).unwrap();
+    assert_eq!(reread.data, salvaged.data);
+    assert_eq!(reread.image, rebuilt.image);
+  }
+
+  #[test]
+  fn cut_in_image() {
+    let (bytes, data_length) = save_bytes();
+    let image_offset = DATA_OFFSET + data_length;
+    let salvaged = SalvagedSave::read(&bytes[..image_offset + 10]).unwrap();
+    assert!(salvaged.is_data_complete());
+    assert_eq!(salvaged.image, None);
+    assert_eq!(salvaged.missing.len(), 1);
+    assert!(salvaged.missing[0].starts_with("image, 10 of "));
+
+    let rebuilt = salvaged.rebuild("rebuilt.dat").unwrap();
+    assert_eq!(rebuilt.data, &bytes[DATA_OFFSET..image_offset]);
+    assert_eq!(
+      rebuilt.image,
+      thumbnail::generate_placeholder_image(TITLE, SUB_TITLE).unwrap()
+    );
+  }
+
+  #[test]
+  fn damaged_image() {
+    let (mut bytes, data_length) = save_bytes();
+    bytes[DATA_OFFSET + data_length..DATA_OFFSET + data_length + 4].copy_from_slice(b"JUNK");
+
+    let salvaged = SalvagedSave::read(&bytes).unwrap();
+    assert!(salvaged.is_data_complete());
+    assert_eq!(salvaged.image, None);
+    assert!(salvaged.missing[0].starts_with("image, "));
+    assert!(salvaged.rebuild("rebuilt.dat").is_ok());
+  }
+}
@@ -0,0 +1,196 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+
+use color_eyre::eyre::{bail, eyre, WrapErr};
+
+static TITLE_SEPARATOR: &str = " - ";
+static LEVEL_PREFIX: &str = "Level ";
+pub static MAX_TITLE_LENGTH: usize = 0x7F;
+
+/// Structured representation of the save title,
+/// which the game formats as `<quest> - Level <level> - <hours>:<minutes>:<seconds>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SaveTitle {
+  pub quest: String,
+  pub level: u32,
+  pub playtime: Duration,
+}
+
+impl FromStr for SaveTitle {
+  type Err = color_eyre::Report;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    // Quest names may contain the separator themselves, so we split from the right.
+    let mut parts = s.rsplitn(3, TITLE_SEPARATOR);
+    let (Some(playtime), Some(level), Some(quest)) = (parts.next(), parts.next(), parts.next())
+    else {
+      bail!(
+        "invalid save title {:?}, expected \"<quest> - Level <level> - <playtime>\"",
+        s
+      );
+    };
+
+    let level = level
+      .strip_prefix(LEVEL_PREFIX)
+      .ok_or_else(|| eyre!("invalid save title {:?}, missing player level", s))?
+      .parse::<u32>()
+      .wrap_err_with(|| format!("invalid save title {:?}, malformed player level", s))?;
+    let playtime =
+      parse_playtime(playtime).wrap_err_with(|| format!("invalid save title {:?}", s))?;
+
+    Ok(Self {
+      quest: quest.to_owned(),
+      level,
+      playtime,
+    })
+  }
+}
+
+impl Display for SaveTitle {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      fmt,
+      "{}{}{}{}{}{}",
+      self.quest,
+      TITLE_SEPARATOR,
+      LEVEL_PREFIX,
+      self.level,
+      TITLE_SEPARATOR,
+      format_playtime(&self.playtime)
+    )
+  }
+}
+
+impl SaveTitle {
+  /// Formats the title, making sure it fits in the GGDS title field.
+  pub fn format(&self) -> color_eyre::Result<String> {
+    let title = self.to_string();
+
+    if title.len() > MAX_TITLE_LENGTH {
+      bail!(
+        "save title {:?} is too long, expected at most {} bytes but got {}",
+        title,
+        MAX_TITLE_LENGTH,
+        title.len()
+      );
+    }
+
+    Ok(title)
+  }
+}
+
+/// Parses the playtime in the `HH:MM:SS` format used by the game.
+pub fn parse_playtime(s: &str) -> color_eyre::Result<Duration> {
+  let parts = s
+    .split(':')
+    .map(|part| part.parse::<u64>())
+    .collect::<Result<Vec<_>, _>>()
+    .wrap_err_with(|| format!("malformed playtime {:?}, expected HH:MM:SS", s))?;
+
+  match parts[..] {
+    [hours, minutes, seconds] if minutes < 60 && seconds < 60 => {
+      Ok(Duration::from_secs(hours * 3600 + minutes * 60 + seconds))
+    }
+    _ => bail!("malformed playtime {:?}, expected HH:MM:SS", s),
+  }
+}
+
+/// Formats the playtime in the `HH:MM:SS` format used by the game.
+pub fn format_playtime(playtime: &Duration) -> String {
+  let secs = playtime.as_secs();
+
+  format!(
+    "{:02}:{:02}:{:02}",
+    secs / 3600,
+    (secs / 60) % 60,
+    secs % 60
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn title_roundtrip() {
+    let title = "Reach For The Stars - Level 1 - 00:00:00"
+      .parse::<SaveTitle>()
+      .unwrap();
+    assert_eq!(
+      title,
+      SaveTitle {
+        quest: "Reach For The Stars".to_owned(),
+        level: 1,
+        playtime: Duration::ZERO,
+      }
+    );
+    assert_eq!(
+      title.format().unwrap(),
+      "Reach For The Stars - Level 1 - 00:00:00"
+    );
+
+    let title = SaveTitle {
+      quest: "The Point of the Spear".to_owned(),
+      level: 12,
+      playtime: Duration::from_secs(123 * 3600 + 4 * 60 + 5),
+    };
+    assert_eq!(title.format().unwrap().parse::<SaveTitle>().unwrap(), title);
+  }
+
+  #[test]
+  fn quest_name_with_separator() {
+    let title = "Faro's Tomb - Part 2 - Level 30 - 42:00:01"
+      .parse::<SaveTitle>()
+      .unwrap();
+    assert_eq!(title.quest, "Faro's Tomb - Part 2");
+    assert_eq!(title.level, 30);
+    assert_eq!(title.playtime, Duration::from_secs(42 * 3600 + 1));
+  }
+
+  #[test]
+  fn malformed_playtime() {
+    for playtime in [
+      "", "12:00", "12:60:00", "12:00:60", "1:2:3:4", "aa:bb:cc", "-1:00:00",
+    ] {
+      assert!(parse_playtime(playtime).is_err(), "{:?}", playtime);
+    }
+    assert!("Quest - Level 1 - 1h".parse::<SaveTitle>().is_err());
+    assert!("Quest - 1 - 00:00:00".parse::<SaveTitle>().is_err());
+    assert!("Quest".parse::<SaveTitle>().is_err());
+  }
+
+  #[test]
+  fn title_too_long() {
+    let mut title = SaveTitle {
+      quest: String::new(),
+      level: 1,
+      playtime: Duration::ZERO,
+    };
+    let suffix_len = title.to_string().len();
+
+    title.quest = "a".repeat(MAX_TITLE_LENGTH - suffix_len);
+    assert_eq!(title.format().unwrap().len(), MAX_TITLE_LENGTH);
+
+    title.quest.push('a');
+    let err = title.format().unwrap_err();
+    assert!(err.to_string().contains("is too long"), "{}", err);
+  }
+}
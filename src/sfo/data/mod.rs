@@ -15,6 +15,8 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
+use std::fmt::{Display, Formatter};
+
 use binrw::{binread, NullString};
 use derivative::Derivative;
 
@@ -23,7 +25,7 @@ use crate::sfo::data::format::SFODataFormat;
 pub mod format;
 
 #[binread]
-#[derive(Derivative)]
+#[derive(Derivative, PartialEq)]
 #[derivative(Debug)]
 #[br(little)]
 #[br(import { format: SFODataFormat, length: u32, capacity: u32 })]
@@ -39,3 +41,13 @@ pub enum SFOParamData {
   #[br(pre_assert(format == SFODataFormat::Int))]
   Int(u32),
 }
+
+impl Display for SFOParamData {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      SFOParamData::SpecialMode(data) => write!(fmt, "<{} bytes>", data.len()),
+      SFOParamData::Utf8(value) => write!(fmt, "{:?}", value.to_string()),
+      SFOParamData::Int(value) => write!(fmt, "{}", value),
+    }
+  }
+}
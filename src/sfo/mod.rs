@@ -15,26 +15,33 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-use std::collections::HashMap;
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Cursor;
 use std::path::{Path, PathBuf};
 
-use binrw::io::BufReader;
-use binrw::{binread, BinRead};
-use color_eyre::eyre::bail;
+use binrw::{binread, BinRead, NullString};
+use tracing::{debug, instrument, trace, warn};
 
+pub use data::SFOParamData;
 pub use header::SFOHeader;
+pub use writer::write_sfo;
 
 use crate::save::SaveMetadata;
-use crate::sfo::data::SFOParamData;
 use crate::sfo::param::SFOParam;
-use crate::utils::error::ToReport;
+use crate::utils::error::{parse_error_report, KnownError};
 
 mod data;
 mod header;
 mod param;
+mod writer;
 
+pub static SFO_MAGIC: &[u8] = b"\0PSF";
 static SFO_SAVE_DATA_CATEGORY: &str = "sd";
+static SFO_SUB_TITLE_PREFIX: &str = "Horizon Forbidden West™ - ";
+/// Title IDs of the Horizon Forbidden West releases whose saves were checked to convert correctly,
+/// the saves of other releases are recognized by their subtitle instead.
+static HFW_TITLE_IDS: &[&str] = &["CUSA24767"];
 
 #[binread]
 #[derive(Debug)]
@@ -53,10 +60,29 @@ pub struct SFOFile {
 impl SFOFile {
   //noinspection DuplicatedCode
   pub fn open<P: AsRef<Path>>(path: P) -> color_eyre::Result<Self> {
+    let data = fs::read(path.as_ref())?;
+
+    Self::from_bytes(&data, path)
+  }
+
+  /// Parses the `param.sfo` file read from elsewhere, the path is only kept for reference.
+  #[instrument(name = "parse_sfo", level = "debug", skip_all, fields(path = ?path.as_ref()))]
+  pub fn from_bytes<P: AsRef<Path>>(data: &[u8], path: P) -> color_eyre::Result<Self> {
     let mut val =
-      Self::read(&mut BufReader::new(File::open(path.as_ref())?)).map_err(|err| err.to_report())?;
+      Self::read(&mut Cursor::new(data)).map_err(|err| parse_error_report(&err, data))?;
     val.path = path.as_ref().to_path_buf();
 
+    debug!(
+      version = format_args!("{:#x}", val.header.version),
+      entries = val.header.entries_count,
+      "Parsed param.sfo header"
+    );
+    let mut params = val.params.iter().collect::<Vec<_>>();
+    params.sort_by_key(|(key, _)| *key);
+    for (key, value) in params {
+      trace!("  {}: {}", key, value);
+    }
+
     Ok(val)
   }
 }
@@ -67,46 +93,104 @@ impl TryInto<SaveMetadata> for SFOFile {
   fn try_into(self) -> Result<SaveMetadata, Self::Error> {
     if let Some(SFOParamData::Utf8(category)) = self.params.get("CATEGORY") {
       if category.to_string() != SFO_SAVE_DATA_CATEGORY {
-        bail!(
+        return Err(KnownError::ForeignSfo.report(format!(
           "invalid SFO file, expected category to be {:?} but found {:?}",
           SFO_SAVE_DATA_CATEGORY,
           category.to_string()
-        )
+        )));
       }
     } else {
-      bail!("invalid SFO file, missing or invalid CATEGORY param");
+      return Err(
+        KnownError::ForeignSfo.report("invalid SFO file, missing or invalid CATEGORY param"),
+      );
     }
 
     let title = if let Some(SFOParamData::Utf8(title)) = self.params.get("MAINTITLE") {
       title.to_string()
     } else {
-      bail!("invalid SFO file, missing or invalid MAINTITLE param");
+      return Err(
+        KnownError::ForeignSfo.report("invalid SFO file, missing or invalid MAINTITLE param"),
+      );
     };
 
     let sub_title = if let Some(SFOParamData::Utf8(sub_title)) = self.params.get("SUBTITLE") {
-      sub_title
-        .to_string()
-        .replace("Horizon Forbidden West™ - ", "")
+      sub_title.to_string()
     } else {
-      bail!("invalid SFO file, missing or invalid SUBTITLE param");
+      return Err(
+        KnownError::ForeignSfo.report("invalid SFO file, missing or invalid SUBTITLE param"),
+      );
     };
 
+    // The param.sfo files written by the extract command don't carry the title ID.
+    match self.params.get("TITLE_ID") {
+      Some(SFOParamData::Utf8(title_id))
+        if !HFW_TITLE_IDS.contains(&title_id.to_string().as_str()) =>
+      {
+        if !sub_title.starts_with(SFO_SUB_TITLE_PREFIX) {
+          return Err(KnownError::ForeignSfo.report(format!(
+            "invalid SFO file, title ID {:?} is not a known Horizon Forbidden West release and the subtitle {:?} doesn't name the game",
+            title_id.to_string(),
+            sub_title
+          )));
+        }
+
+        warn!(
+          "The param.sfo title ID {:?} is not a known Horizon Forbidden West release, converting it as its subtitle names the game.",
+          title_id.to_string()
+        );
+      }
+      Some(SFOParamData::Utf8(_)) | None => {}
+      Some(_) => {
+        return Err(KnownError::ForeignSfo.report("invalid SFO file, invalid TITLE_ID param"));
+      }
+    }
+    let sub_title = sub_title.replace(SFO_SUB_TITLE_PREFIX, "");
+
     let file_name =
       if let Some(SFOParamData::Utf8(file_name)) = self.params.get("SAVEDATA_DIRECTORY") {
         file_name.to_string().to_lowercase()
       } else {
-        bail!("invalid SFO file, missing or invalid SAVEDATA_DIRECTORY param");
+        return Err(
+          KnownError::ForeignSfo
+            .report("invalid SFO file, missing or invalid SAVEDATA_DIRECTORY param"),
+        );
       };
 
     let checksum = if let Some(SFOParamData::Int(checksum)) = self.params.get("SAVEDATA_LIST_PARAM")
     {
       *checksum
     } else {
-      bail!("invalid SFO file, missing or invalid SAVEDATA_LIST_PARAM param");
+      return Err(
+        KnownError::ForeignSfo
+          .report("invalid SFO file, missing or invalid SAVEDATA_LIST_PARAM param"),
+      );
     };
 
-    Ok(SaveMetadata::new(
-      file_name, title, sub_title, checksum, None,
-    ))
+    Ok(SaveMetadata::new(file_name, title, sub_title, checksum))
+  }
+}
+
+impl From<&SaveMetadata> for BTreeMap<String, SFOParamData> {
+  /// Builds the `param.sfo` params describing the save,
+  /// only the params the metadata was resolved from can be restored.
+  fn from(metadata: &SaveMetadata) -> Self {
+    let utf8 = |value: &str| SFOParamData::Utf8(NullString::from(value));
+
+    BTreeMap::from([
+      ("CATEGORY".to_owned(), utf8(SFO_SAVE_DATA_CATEGORY)),
+      ("MAINTITLE".to_owned(), utf8(&metadata.title)),
+      (
+        "SUBTITLE".to_owned(),
+        utf8(&format!("{}{}", SFO_SUB_TITLE_PREFIX, metadata.sub_title)),
+      ),
+      (
+        "SAVEDATA_DIRECTORY".to_owned(),
+        utf8(&metadata.file_name.to_uppercase()),
+      ),
+      (
+        "SAVEDATA_LIST_PARAM".to_owned(),
+        SFOParamData::Int(metadata.checksum),
+      ),
+    ])
   }
 }
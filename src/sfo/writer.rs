@@ -0,0 +1,99 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::bail;
+
+use crate::sfo::data::format::SFODataFormat;
+use crate::sfo::{SFOParamData, SFO_MAGIC};
+
+static SFO_VERSION: u32 = 0x101;
+static HEADER_SIZE: usize = 0x14;
+static ENTRY_SIZE: usize = 0x10;
+
+/// Capacities reserved for the text params in the save data `param.sfo` files,
+/// other text params are padded to the next 4 bytes.
+static UTF8_CAPACITIES: [(&str, usize); 6] = [
+  ("CATEGORY", 4),
+  ("DETAIL", 1024),
+  ("MAINTITLE", 128),
+  ("SAVEDATA_DIRECTORY", 32),
+  ("SUBTITLE", 128),
+  ("TITLE_ID", 16),
+];
+
+/// Serializes the params into a `param.sfo` file, the params are written sorted by their keys.
+pub fn write_sfo(params: &BTreeMap<String, SFOParamData>) -> color_eyre::Result<Vec<u8>> {
+  let mut entries = vec![];
+  let mut keys = vec![];
+  let mut data = vec![];
+
+  for (key, value) in params {
+    let (format, mut bytes, capacity) = match value {
+      SFOParamData::SpecialMode(bytes) => (SFODataFormat::SpecialMode, bytes.clone(), bytes.len()),
+      SFOParamData::Utf8(text) => {
+        let mut bytes = text.0.clone();
+        bytes.push(0);
+        let capacity = UTF8_CAPACITIES
+          .iter()
+          .find(|(name, _)| name == key)
+          .map(|(_, capacity)| *capacity)
+          .unwrap_or(bytes.len().next_multiple_of(4));
+
+        if bytes.len() > capacity {
+          bail!(
+            "SFO param {} is too long, {} bytes don't fit in {} bytes",
+            key,
+            bytes.len(),
+            capacity
+          );
+        }
+
+        (SFODataFormat::Utf8, bytes, capacity)
+      }
+      SFOParamData::Int(value) => (SFODataFormat::Int, value.to_le_bytes().to_vec(), 4),
+    };
+
+    entries.extend_from_slice(&(keys.len() as u16).to_le_bytes());
+    entries.extend_from_slice(&(format as u16).to_le_bytes());
+    entries.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    entries.extend_from_slice(&(capacity as u32).to_le_bytes());
+    entries.extend_from_slice(&(data.len() as u32).to_le_bytes());
+
+    keys.extend_from_slice(key.as_bytes());
+    keys.push(0);
+    bytes.resize(capacity, 0);
+    data.extend_from_slice(&bytes);
+  }
+  keys.resize(keys.len().next_multiple_of(4), 0);
+
+  let key_table_offset = HEADER_SIZE + params.len() * ENTRY_SIZE;
+  let data_table_offset = key_table_offset + keys.len();
+
+  let mut output = Vec::with_capacity(data_table_offset + data.len());
+  output.extend_from_slice(SFO_MAGIC);
+  output.extend_from_slice(&SFO_VERSION.to_le_bytes());
+  output.extend_from_slice(&(key_table_offset as u32).to_le_bytes());
+  output.extend_from_slice(&(data_table_offset as u32).to_le_bytes());
+  output.extend_from_slice(&(params.len() as u32).to_le_bytes());
+  output.extend_from_slice(&entries);
+  output.extend_from_slice(&keys);
+  output.extend_from_slice(&data);
+
+  Ok(output)
+}
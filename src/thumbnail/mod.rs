@@ -0,0 +1,188 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use color_eyre::eyre::WrapErr;
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::imageops;
+use image::io::Reader as ImageReader;
+use image::{ColorType, ExtendedColorType, ImageEncoder, ImageFormat, RgbImage};
+use tracing::{debug, instrument, warn};
+
+pub use placeholder::generate_placeholder_image;
+
+mod placeholder;
+
+pub static THUMBNAIL_WIDTH: u32 = 480;
+pub static THUMBNAIL_HEIGHT: u32 = 270;
+static ASPECT_RATIO_TOLERANCE: f64 = 0.01;
+
+/// Reads the save image, normalizing it unless it should be embedded as is.
+#[instrument(name = "load_image", level = "debug", skip_all, fields(path = ?path.as_ref(), raw))]
+pub fn load<P: AsRef<Path>>(path: P, raw: bool) -> color_eyre::Result<Vec<u8>> {
+  let path = path.as_ref();
+  let data = fs::read(path).wrap_err_with(|| format!("failed to read save image {:?}", path))?;
+
+  prepare(data, raw).wrap_err_with(|| format!("failed to process save image {:?}", path))
+}
+
+/// Normalizes the save image read from elsewhere, unless it should be embedded as is.
+pub fn prepare(data: Vec<u8>, raw: bool) -> color_eyre::Result<Vec<u8>> {
+  if raw {
+    Ok(data)
+  } else {
+    normalize(&data)
+  }
+}
+
+/// Decodes the image in any of the supported formats and letterboxes it
+/// into a PNG image with the size of the thumbnails generated by the PC version of the game.
+pub fn normalize(data: &[u8]) -> color_eyre::Result<Vec<u8>> {
+  let reader = ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .wrap_err("failed to detect save image format")?;
+  let format = reader.format();
+  debug!("Decoding {:?} save image..", format);
+  let source = reader.decode().wrap_err("failed to decode save image")?;
+
+  // Re-encoding an image that is already a thumbnail would only change its bytes.
+  if format == Some(ImageFormat::Png)
+    && source.color() == ColorType::Rgb8
+    && (source.width(), source.height()) == (THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+  {
+    debug!("Save image is already in the PC thumbnail format, keeping it as is.");
+    return Ok(data.to_vec());
+  }
+  let source = source.to_rgb8();
+
+  let (width, height) = source.dimensions();
+  let source_ratio = width as f64 / height as f64;
+  let target_ratio = THUMBNAIL_WIDTH as f64 / THUMBNAIL_HEIGHT as f64;
+  if (source_ratio / target_ratio - 1.0).abs() > ASPECT_RATIO_TOLERANCE {
+    warn!(
+      "Save image is {}x{}, which doesn't match the {}x{} aspect ratio, it will be letterboxed.",
+      width, height, THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT
+    );
+  }
+
+  let scale = f64::min(
+    THUMBNAIL_WIDTH as f64 / width as f64,
+    THUMBNAIL_HEIGHT as f64 / height as f64,
+  );
+  let scaled_width = ((width as f64 * scale).round() as u32).clamp(1, THUMBNAIL_WIDTH);
+  let scaled_height = ((height as f64 * scale).round() as u32).clamp(1, THUMBNAIL_HEIGHT);
+  let scaled = imageops::resize(
+    &source,
+    scaled_width,
+    scaled_height,
+    imageops::FilterType::Lanczos3,
+  );
+
+  let mut buf = RgbImage::new(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT);
+  imageops::overlay(
+    &mut buf,
+    &scaled,
+    ((THUMBNAIL_WIDTH - scaled_width) / 2) as i64,
+    ((THUMBNAIL_HEIGHT - scaled_height) / 2) as i64,
+  );
+
+  encode_png(&buf).wrap_err("failed to encode save image")
+}
+
+fn encode_png(buf: &RgbImage) -> color_eyre::Result<Vec<u8>> {
+  let mut raw_img = vec![];
+  PngEncoder::new_with_quality(&mut raw_img, CompressionType::Best, FilterType::NoFilter)
+    .write_image(buf, buf.width(), buf.height(), ExtendedColorType::Rgb8)?;
+
+  Ok(raw_img)
+}
+
+#[cfg(test)]
+mod tests {
+  use image::Rgb;
+
+  use super::*;
+  use crate::log::capture_warnings;
+
+  static WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+  static BLACK: Rgb<u8> = Rgb([0, 0, 0]);
+
+  fn png(width: u32, height: u32) -> Vec<u8> {
+    encode_png(&RgbImage::from_pixel(width, height, WHITE)).unwrap()
+  }
+
+  fn decode(data: &[u8]) -> RgbImage {
+    image::load_from_memory(data).unwrap().to_rgb8()
+  }
+
+  #[test]
+  fn images_with_another_aspect_ratio_are_letterboxed() {
+    let (thumbnail, warnings) = capture_warnings(|| normalize(&png(100, 100)).unwrap());
+    let thumbnail = decode(&thumbnail);
+
+    assert_eq!(thumbnail.dimensions(), (THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT));
+    // The square image is scaled to 270x270 and centered between two black bars.
+    assert_eq!(*thumbnail.get_pixel(0, 135), BLACK);
+    assert_eq!(*thumbnail.get_pixel(100, 135), BLACK);
+    assert_eq!(*thumbnail.get_pixel(240, 135), WHITE);
+    assert_eq!(*thumbnail.get_pixel(379, 135), BLACK);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("doesn't match the 480x270 aspect ratio"));
+  }
+
+  #[test]
+  fn images_with_the_thumbnail_aspect_ratio_are_scaled() {
+    let (thumbnail, warnings) = capture_warnings(|| normalize(&png(1920, 1080)).unwrap());
+    let thumbnail = decode(&thumbnail);
+
+    assert_eq!(thumbnail.dimensions(), (THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT));
+    assert_eq!(*thumbnail.get_pixel(0, 0), WHITE);
+    assert!(warnings.is_empty());
+  }
+
+  #[test]
+  fn thumbnail_pngs_pass_through() {
+    // Encoded differently than the normalized images, so re-encoding it would change the bytes.
+    let mut thumbnail = vec![];
+    PngEncoder::new_with_quality(&mut thumbnail, CompressionType::Fast, FilterType::Sub)
+      .write_image(
+        &RgbImage::from_pixel(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, WHITE),
+        THUMBNAIL_WIDTH,
+        THUMBNAIL_HEIGHT,
+        ExtendedColorType::Rgb8,
+      )
+      .unwrap();
+    assert_ne!(thumbnail, png(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT));
+
+    assert_eq!(prepare(thumbnail.clone(), false).unwrap(), thumbnail);
+  }
+
+  #[test]
+  fn raw_images_are_kept_as_is() {
+    let image = png(100, 100);
+
+    assert_eq!(prepare(image.clone(), true).unwrap(), image);
+    assert_eq!(
+      prepare(b"not an image".to_vec(), true).unwrap(),
+      b"not an image"
+    );
+    assert!(prepare(b"not an image".to_vec(), false).is_err());
+  }
+}
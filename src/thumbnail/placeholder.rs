@@ -0,0 +1,316 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use color_eyre::eyre::WrapErr;
+use image::{Rgb, RgbImage};
+use tracing::instrument;
+
+use crate::save::{format_playtime, SaveTitle};
+use crate::thumbnail::{encode_png, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+
+static FONT_REGULAR: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+static FONT_BOLD: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans-Bold.ttf");
+
+static PADDING: f32 = 28.0;
+static HEADING_SCALE: f32 = 32.0;
+static HEADING_MAX_LINES: usize = 2;
+static DETAILS_SCALE: f32 = 22.0;
+static BADGE_SCALE: f32 = 16.0;
+static BADGE_PADDING: f32 = 8.0;
+static BADGE_TEXT: &str = "Converted from PS4";
+static ELLIPSIS: &str = "…";
+
+static BACKGROUND_TOP: Rgb<u8> = Rgb([0x1b, 0x1e, 0x28]);
+static BACKGROUND_BOTTOM: Rgb<u8> = Rgb([0x30, 0x34, 0x46]);
+static HEADING_COLOR: Rgb<u8> = Rgb([0xe4, 0xf0, 0xfb]);
+static DETAILS_COLOR: Rgb<u8> = Rgb([0xad, 0xd7, 0xff]);
+static SUB_TITLE_COLOR: Rgb<u8> = Rgb([0xa6, 0xac, 0xcd]);
+static BADGE_COLOR: Rgb<u8> = Rgb([0x5d, 0xe4, 0xc7]);
+static BADGE_TEXT_COLOR: Rgb<u8> = Rgb([0x1b, 0x1e, 0x28]);
+
+/// Generates a thumbnail showing the save title and subtitle,
+/// so saves without an image can still be told apart in the in-game load menu.
+#[instrument(name = "generate_image", level = "debug", skip(sub_title))]
+pub fn generate_placeholder_image(title: &str, sub_title: &str) -> color_eyre::Result<Vec<u8>> {
+  let regular = FontRef::try_from_slice(FONT_REGULAR).wrap_err("failed to load embedded font")?;
+  let bold = FontRef::try_from_slice(FONT_BOLD).wrap_err("failed to load embedded font")?;
+
+  let mut buf = RgbImage::from_fn(THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT, |_, y| {
+    lerp(
+      BACKGROUND_TOP,
+      BACKGROUND_BOTTOM,
+      y as f32 / THUMBNAIL_HEIGHT as f32,
+    )
+  });
+  let max_width = THUMBNAIL_WIDTH as f32 - 2.0 * PADDING;
+
+  let (heading, details) = match title.parse::<SaveTitle>() {
+    Ok(title) => (
+      title.quest,
+      Some(format!(
+        "Level {} · {}",
+        title.level,
+        format_playtime(&title.playtime)
+      )),
+    ),
+    Err(_) => (title.to_owned(), None),
+  };
+
+  let mut y = PADDING;
+  for line in wrap_text(&bold, HEADING_SCALE, &heading, max_width, HEADING_MAX_LINES) {
+    draw_text(
+      &mut buf,
+      &bold,
+      HEADING_SCALE,
+      PADDING,
+      y,
+      &line,
+      HEADING_COLOR,
+    );
+    y += line_height(&bold, HEADING_SCALE);
+  }
+
+  y += PADDING / 2.0;
+  for (text, color) in details
+    .iter()
+    .map(|details| (details.as_str(), DETAILS_COLOR))
+    .chain([(sub_title, SUB_TITLE_COLOR)])
+  {
+    for line in wrap_text(&regular, DETAILS_SCALE, text, max_width, 1) {
+      draw_text(&mut buf, &regular, DETAILS_SCALE, PADDING, y, &line, color);
+      y += line_height(&regular, DETAILS_SCALE);
+    }
+  }
+
+  let badge_height = line_height(&bold, BADGE_SCALE) + 2.0 * BADGE_PADDING;
+  let badge_width = text_width(&bold, BADGE_SCALE, BADGE_TEXT) + 2.0 * BADGE_PADDING;
+  let badge_x = PADDING;
+  let badge_y = THUMBNAIL_HEIGHT as f32 - PADDING - badge_height;
+  fill_rect(
+    &mut buf,
+    badge_x,
+    badge_y,
+    badge_width,
+    badge_height,
+    BADGE_COLOR,
+  );
+  draw_text(
+    &mut buf,
+    &bold,
+    BADGE_SCALE,
+    badge_x + BADGE_PADDING,
+    badge_y + BADGE_PADDING,
+    BADGE_TEXT,
+    BADGE_TEXT_COLOR,
+  );
+
+  encode_png(&buf).wrap_err("failed to generate placeholder save image")
+}
+
+fn line_height(font: &FontRef, scale: f32) -> f32 {
+  let scaled = font.as_scaled(PxScale::from(scale));
+  scaled.height() + scaled.line_gap()
+}
+
+fn text_width(font: &FontRef, scale: f32, text: &str) -> f32 {
+  let scaled = font.as_scaled(PxScale::from(scale));
+  let mut width = 0.0;
+  let mut previous = None;
+
+  for char in text.chars() {
+    let id = scaled.glyph_id(char);
+    if let Some(previous) = previous {
+      width += scaled.kern(previous, id);
+    }
+    width += scaled.h_advance(id);
+    previous = Some(id);
+  }
+
+  width
+}
+
+/// Wraps the text on word boundaries, ending the last line with an ellipsis if the text doesn't fit,
+/// as well as any line holding a single word longer than the line.
+fn wrap_text(
+  font: &FontRef,
+  scale: f32,
+  text: &str,
+  max_width: f32,
+  max_lines: usize,
+) -> Vec<String> {
+  let mut lines: Vec<String> = vec![];
+
+  for word in text.split_whitespace() {
+    let append = lines.last().is_some_and(|line| {
+      lines.len() >= max_lines
+        || text_width(font, scale, &format!("{} {}", line, word)) <= max_width
+    });
+
+    match lines.last_mut() {
+      Some(line) if append => {
+        line.push(' ');
+        line.push_str(word);
+      }
+      _ => lines.push(word.to_owned()),
+    }
+  }
+
+  for line in &mut lines {
+    if text_width(font, scale, line) > max_width {
+      while !line.is_empty()
+        && text_width(font, scale, &format!("{}{}", line, ELLIPSIS)) > max_width
+      {
+        line.pop();
+      }
+      line.truncate(line.trim_end().len());
+      line.push_str(ELLIPSIS);
+    }
+  }
+
+  lines
+}
+
+fn draw_text(
+  buf: &mut RgbImage,
+  font: &FontRef,
+  scale: f32,
+  x: f32,
+  y: f32,
+  text: &str,
+  color: Rgb<u8>,
+) {
+  let px_scale = PxScale::from(scale);
+  let scaled = font.as_scaled(px_scale);
+  let mut caret = point(x, y + scaled.ascent());
+  let mut previous = None;
+
+  for char in text.chars() {
+    let id = scaled.glyph_id(char);
+    if let Some(previous) = previous {
+      caret.x += scaled.kern(previous, id);
+    }
+    let glyph = id.with_scale_and_position(px_scale, caret);
+    caret.x += scaled.h_advance(id);
+    previous = Some(id);
+
+    if let Some(outlined) = font.outline_glyph(glyph) {
+      let bounds = outlined.px_bounds();
+      outlined.draw(|glyph_x, glyph_y, coverage| {
+        let pixel_x = bounds.min.x as i64 + glyph_x as i64;
+        let pixel_y = bounds.min.y as i64 + glyph_y as i64;
+
+        if (0..buf.width() as i64).contains(&pixel_x) && (0..buf.height() as i64).contains(&pixel_y)
+        {
+          let pixel = buf.get_pixel_mut(pixel_x as u32, pixel_y as u32);
+          *pixel = lerp(*pixel, color, coverage);
+        }
+      });
+    }
+  }
+}
+
+fn fill_rect(buf: &mut RgbImage, x: f32, y: f32, width: f32, height: f32, color: Rgb<u8>) {
+  let (x, y) = (x.round().max(0.0) as u32, y.round().max(0.0) as u32);
+  let (x_end, y_end) = (
+    (x + width.round() as u32).min(buf.width()),
+    (y + height.round() as u32).min(buf.height()),
+  );
+
+  for pixel_y in y..y_end {
+    for pixel_x in x..x_end {
+      buf.put_pixel(pixel_x, pixel_y, color);
+    }
+  }
+}
+
+fn lerp(from: Rgb<u8>, to: Rgb<u8>, t: f32) -> Rgb<u8> {
+  let t = t.clamp(0.0, 1.0);
+  Rgb([0, 1, 2].map(|i| (from[i] as f32 + (to[i] as f32 - from[i] as f32) * t).round() as u8))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  static MAX_WIDTH: f32 = 200.0;
+
+  fn font() -> FontRef<'static> {
+    FontRef::try_from_slice(FONT_REGULAR).unwrap()
+  }
+
+  fn wrap(text: &str, max_lines: usize) -> Vec<String> {
+    wrap_text(&font(), DETAILS_SCALE, text, MAX_WIDTH, max_lines)
+  }
+
+  fn fits(line: &str) -> bool {
+    text_width(&font(), DETAILS_SCALE, line) <= MAX_WIDTH
+  }
+
+  #[test]
+  fn text_is_wrapped_on_word_boundaries() {
+    let text = "The Point of the Spear and the Sea of Sands";
+    let lines = wrap(text, 4);
+
+    assert!(lines.len() > 1);
+    assert!(lines.iter().all(|line| fits(line)));
+    assert!(lines.iter().all(|line| !line.ends_with(ELLIPSIS)));
+    assert_eq!(lines.join(" "), text);
+  }
+
+  #[test]
+  fn text_that_doesnt_fit_ends_with_an_ellipsis() {
+    let lines = wrap(
+      "The Point of the Spear and the Sea of Sands and the Broken Sky",
+      2,
+    );
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| fits(line)));
+    assert!(!lines[0].ends_with(ELLIPSIS));
+    assert!(lines[1].ends_with(ELLIPSIS));
+    assert!(!lines[1].ends_with(&format!(" {}", ELLIPSIS)));
+  }
+
+  #[test]
+  fn words_longer_than_the_line_are_cut() {
+    let word = "Supercalifragilisticexpialidocious";
+    let lines = wrap(&format!("{} Spear", word), 2);
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines.iter().all(|line| fits(line)));
+    assert!(lines[0].ends_with(ELLIPSIS));
+    assert!(word.starts_with(lines[0].trim_end_matches(ELLIPSIS)));
+    assert_eq!(lines[1], "Spear");
+  }
+
+  #[test]
+  fn placeholder_image_is_a_thumbnail_png() {
+    let data =
+      generate_placeholder_image("The Point of the Spear - Level 12 - 05:06:07", "Autosave")
+        .unwrap();
+    let image = image::load_from_memory(&data).unwrap();
+
+    assert!(data.starts_with(b"\x89PNG"));
+    assert_eq!(
+      (image.width(), image.height()),
+      (THUMBNAIL_WIDTH, THUMBNAIL_HEIGHT)
+    );
+    // Titles that don't follow the save title format are shown as they are.
+    assert!(generate_placeholder_image("Custom title", "").is_ok());
+  }
+}
@@ -21,12 +21,156 @@ use std::fmt::{Display, Formatter};
 use binrw::error::BacktraceFrame;
 use binrw::Error;
 use color_eyre::eyre::eyre;
-use color_eyre::Report;
+use color_eyre::{Report, Section, SectionExt};
+use serde::Serialize;
+
+use crate::utils::fmt::hex_dump;
+
+/// Failures with a known cause, reported together with the instructions on how to fix them.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KnownError {
+  /// The selected path doesn't contain the save data.
+  WrongFolder,
+  /// The save data wasn't decrypted before the conversion.
+  EncryptedSave,
+  /// The `param.sfo` file doesn't describe a save of this game.
+  ForeignSfo,
+  /// The output save file already exists.
+  OutputExists,
+}
+
+impl KnownError {
+  pub fn suggestion(self) -> &'static str {
+    match self {
+      KnownError::WrongFolder => "Select the PS4 save directory containing the checkpoint.dat file and the sce_sys directory, or the checkpoint.dat file itself.",
+      KnownError::EncryptedSave => "Decrypt the save on a PS4 with a save manager like Apollo Save Tool, and convert the decrypted checkpoint.dat file.",
+      KnownError::ForeignSfo => "Make sure the sce_sys directory was copied from the same Horizon Forbidden West save as the checkpoint.dat file.",
+      KnownError::OutputExists => "Use --force to overwrite the existing save file, or choose a different output directory with --output.",
+    }
+  }
+
+  pub fn note(self) -> &'static str {
+    match self {
+      KnownError::WrongFolder => "Decrypted saves are exported into a directory named after the save slot, e.g. AUTOSAVE0.",
+      KnownError::EncryptedSave => "If the save data is already decrypted, the check can be skipped with --skip-payload-check.",
+      KnownError::ForeignSfo => "Without the param.sfo file the metadata is derived from the save directory name, and can be set with --title and --sub-title.",
+      KnownError::OutputExists => "Overwriting a save file replaces the in-game progress stored in that slot.",
+    }
+  }
+
+  /// Creates the error report with the suggestion and note sections attached.
+  #[track_caller]
+  pub fn report<D: Display>(self, message: D) -> Report {
+    Report::new(KnownFailure {
+      kind: self,
+      message: message.to_string(),
+    })
+    .suggestion(self.suggestion())
+    .note(self.note())
+  }
+
+  /// Finds the known failure anywhere in the error chain.
+  pub fn find(report: &Report) -> Option<KnownError> {
+    report
+      .chain()
+      .find_map(|err| err.downcast_ref::<KnownFailure>())
+      .map(|failure| failure.kind)
+  }
+}
+
+#[derive(Debug)]
+struct KnownFailure {
+  kind: KnownError,
+  message: String,
+}
+
+impl Display for KnownFailure {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(fmt, "{}", self.message)
+  }
+}
+
+impl std::error::Error for KnownFailure {}
+
+static HEX_DUMP_CONTEXT_LINES: usize = 2;
 
 pub trait ToReport {
   fn to_report(&self) -> Report;
 }
 
+/// Where and what the parser was reading when it failed.
+#[derive(Serialize, Debug, Clone)]
+pub struct ParseContext {
+  pub offset: u64,
+  pub field: Option<String>,
+  pub hex_dump: Vec<String>,
+}
+
+impl Display for ParseContext {
+  fn fmt(&self, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+    match &self.field {
+      Some(field) => write!(
+        fmt,
+        "failed to parse {} at offset 0x{:x}",
+        field, self.offset
+      ),
+      None => write!(
+        fmt,
+        "failed to parse the data at offset 0x{:x}",
+        self.offset
+      ),
+    }
+  }
+}
+
+impl ParseContext {
+  /// Resolves the context of the error from the data that was parsed,
+  /// errors without a position (like the unexpected end of the file) point to the end of the data.
+  pub fn new(error: &Error, data: &[u8]) -> Self {
+    let offset = match error.root_cause() {
+      Error::BadMagic { pos, .. }
+      | Error::AssertFail { pos, .. }
+      | Error::Custom { pos, .. }
+      | Error::NoVariantMatch { pos }
+      | Error::EnumErrors { pos, .. } => *pos,
+      _ => data.len() as u64,
+    };
+    let field = match error {
+      Error::Backtrace(backtrace) => backtrace.frames.iter().find_map(|frame| match frame {
+        BacktraceFrame::Full { message, .. } | BacktraceFrame::Message(message) => message
+          .strip_prefix("While parsing ")
+          .map(|field| field.to_owned()),
+        BacktraceFrame::Custom(_) => None,
+      }),
+      _ => None,
+    };
+
+    Self {
+      offset,
+      field,
+      hex_dump: hex_dump(data, offset as usize, HEX_DUMP_CONTEXT_LINES),
+    }
+  }
+
+  /// Finds the parse context anywhere in the error chain.
+  pub fn find(report: &Report) -> Option<&ParseContext> {
+    report.downcast_ref::<ParseContext>()
+  }
+}
+
+/// Converts the parse error into a report including the offset, field and a hex dump of the data around the failure.
+#[track_caller]
+pub fn parse_error_report(error: &Error, data: &[u8]) -> Report {
+  let context = ParseContext::new(error, data);
+  let hex_dump = context.hex_dump.join("\n");
+
+  error
+    .to_report()
+    .wrap_err(context)
+    .section(hex_dump.header("Hex dump:"))
+}
+
 impl ToReport for Error {
   fn to_report(&self) -> Report {
     match &self {
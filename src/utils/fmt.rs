@@ -18,6 +18,7 @@
 use std::fmt::{Debug, Formatter};
 
 static VEC_DEBUG_LIMIT: usize = 10;
+static HEX_DUMP_WIDTH: usize = 16;
 
 enum OrMore<T> {
   Value(T),
@@ -33,7 +34,7 @@ impl<T: Debug> Debug for OrMore<T> {
   }
 }
 
-pub fn debug_vec<V: Debug>(val: &Vec<V>, fmt: &mut Formatter<'_>) -> std::fmt::Result {
+pub fn debug_vec<V: Debug>(val: &[V], fmt: &mut Formatter<'_>) -> std::fmt::Result {
   if val.len() <= VEC_DEBUG_LIMIT {
     fmt
       .debug_list()
@@ -52,3 +53,89 @@ pub fn debug_vec<V: Debug>(val: &Vec<V>, fmt: &mut Formatter<'_>) -> std::fmt::R
       .finish()
   }
 }
+
+pub fn debug_option_vec<V: Debug>(
+  val: &Option<Vec<V>>,
+  fmt: &mut Formatter<'_>,
+) -> std::fmt::Result {
+  match val {
+    Some(val) => {
+      write!(fmt, "Some(")?;
+      debug_vec(val, fmt)?;
+      write!(fmt, ")")
+    }
+    None => write!(fmt, "None"),
+  }
+}
+
+/// Formats the lines of data around the offset as a hex dump, marking the byte at the offset.
+pub fn hex_dump(data: &[u8], offset: usize, context_lines: usize) -> Vec<String> {
+  let offset_line = offset / HEX_DUMP_WIDTH;
+  let first_line = offset_line.saturating_sub(context_lines);
+  // An offset right after the data still gets a line of its own, so the end of the data can be marked.
+  let last_line = (offset_line + context_lines)
+    .min((data.len().saturating_sub(1) / HEX_DUMP_WIDTH).max(offset_line));
+
+  let mut lines = vec![];
+  for line in first_line..=last_line {
+    let start = line * HEX_DUMP_WIDTH;
+    let chunk = &data[start.min(data.len())..(start + HEX_DUMP_WIDTH).min(data.len())];
+    let hex = chunk
+      .iter()
+      .map(|byte| format!("{:02x}", byte))
+      .collect::<Vec<_>>()
+      .join(" ");
+    let ascii = chunk
+      .iter()
+      .map(|&byte| match byte {
+        0x20..=0x7e => byte as char,
+        _ => '.',
+      })
+      .collect::<String>();
+
+    lines.push(format!(
+      "{:08x}  {:<width$}  |{}|",
+      start,
+      hex,
+      ascii,
+      width = HEX_DUMP_WIDTH * 3 - 1
+    ));
+    if line == offset_line {
+      lines.push(format!("{}^^", " ".repeat(10 + (offset - start) * 3)));
+    }
+  }
+
+  lines
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn hex_dump_marks_offset() {
+    let data = (0..40).collect::<Vec<u8>>();
+    let lines = hex_dump(&data, 0x13, 1);
+
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("00000000  00 01 02"));
+    assert!(lines[1].starts_with("00000010  10 11 12 13"));
+    assert_eq!(lines[2], format!("{}^^", " ".repeat(10 + 3 * 3)));
+    assert!(lines[3].starts_with("00000020  20 21"));
+  }
+
+  #[test]
+  fn hex_dump_marks_end_of_data() {
+    let data = vec![0x41; 32];
+
+    let lines = hex_dump(&data, 32, 1);
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("00000010  41"));
+    assert_eq!(lines[1], format!("00000020  {}  ||", " ".repeat(47)));
+    assert_eq!(lines[2], format!("{}^^", " ".repeat(10)));
+
+    let lines = hex_dump(&data[..30], 30, 1);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[2], format!("{}^^", " ".repeat(10 + 14 * 3)));
+  }
+}
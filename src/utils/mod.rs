@@ -15,68 +15,23 @@
  * this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-pub mod error;
-pub mod fmt;
-
-static DEFAULT_IMAGE_WIDTH: u32 = 480;
-static DEFAULT_IMAGE_HEIGHT: u32 = 270;
-
-#[cfg(not(all(target_os = "windows", target_env = "msvc")))]
-pub fn generate_blank_image() -> color_eyre::Result<Vec<u8>> {
-  use color_eyre::eyre::WrapErr;
-  use image::codecs::png::{CompressionType, FilterType, PngEncoder};
-  use image::{ExtendedColorType, ImageEncoder, RgbImage};
+use std::io::Cursor;
 
-  let mut buf = RgbImage::new(DEFAULT_IMAGE_WIDTH, DEFAULT_IMAGE_HEIGHT);
+use color_eyre::eyre::WrapErr;
+use image::io::Reader as ImageReader;
+use sha2::{Digest, Sha256};
 
-  for pixel in buf.pixels_mut() {
-    *pixel = image::Rgb([0, 0, 0]);
-  }
-
-  let mut raw_img = vec![];
-  PngEncoder::new_with_quality(&mut raw_img, CompressionType::Best, FilterType::NoFilter)
-    .write_image(
-      &buf,
-      DEFAULT_IMAGE_WIDTH,
-      DEFAULT_IMAGE_HEIGHT,
-      ExtendedColorType::Rgb8,
-    )
-    .wrap_err("failed to generate blank save image")?;
+pub mod error;
+pub mod fmt;
 
-  Ok(raw_img)
+pub fn sha256_hex(data: &[u8]) -> String {
+  format!("{:x}", Sha256::digest(data))
 }
 
-#[cfg(all(target_os = "windows", target_env = "msvc"))]
-pub fn generate_blank_image() -> color_eyre::Result<Vec<u8>> {
-  Ok(vec![
-    0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
-    0x00, 0x00, 0x01, 0xE0, 0x00, 0x00, 0x01, 0x0E, 0x08, 0x02, 0x00, 0x00, 0x00, 0xDE, 0xF8, 0x53,
-    0x30, 0x00, 0x00, 0x01, 0x8F, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9C, 0xED, 0xC1, 0x01, 0x0D, 0x00,
-    0x00, 0x00, 0xC2, 0xA0, 0xF7, 0x4F, 0xED, 0xEC, 0x01, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-    0x00, 0x00, 0xC0, 0x0D, 0xF0, 0x19, 0x00, 0x01, 0x61, 0x18, 0xB6, 0xEC, 0x00, 0x00, 0x00, 0x00,
-    0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
-  ])
+pub fn image_dimensions(data: &[u8]) -> color_eyre::Result<(u32, u32)> {
+  ImageReader::new(Cursor::new(data))
+    .with_guessed_format()
+    .wrap_err("failed to detect save image format")?
+    .into_dimensions()
+    .wrap_err("failed to decode save image")
 }
@@ -0,0 +1,259 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use common::{ps4_save, run, stderr};
+
+mod common;
+
+/// Packs the PS4 saves from the directory into a zip archive.
+fn pack_zip(dir: &Path, slots: &[&str], path: &Path) {
+  let mut zip = ZipWriter::new(File::create(path).unwrap());
+  for slot in slots {
+    for file in ["checkpoint.dat", "sce_sys/param.sfo", "sce_sys/icon0.png"] {
+      let Ok(data) = fs::read(dir.join(slot).join(file)) else {
+        continue;
+      };
+
+      zip
+        .start_file(format!("saves/{}/{}", slot, file), FileOptions::default())
+        .unwrap();
+      zip.write_all(&data).unwrap();
+    }
+  }
+  zip.finish().unwrap();
+}
+
+#[test]
+fn convert_reads_saves_from_a_zip_archive() {
+  let dir = tempfile::tempdir().unwrap();
+  ps4_save(dir.path(), "AUTOSAVE0", true);
+  ps4_save(dir.path(), "MANUALSAVE1", false);
+  let archive = dir.path().join("saves.zip");
+  pack_zip(dir.path(), &["AUTOSAVE0", "MANUALSAVE1"], &archive);
+  let output_dir = dir.path().join("pc");
+
+  let output = run([archive.as_os_str(), "-o".as_ref(), output_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  for name in ["autosave0.dat", "manualsave1.dat"] {
+    let data = fs::read(output_dir.join(name)).unwrap();
+    assert_eq!(&data[..4], b"GGDS");
+  }
+}
+
+#[test]
+fn convert_writes_saves_from_a_tarball_to_a_zip_archive() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE2", true);
+  let archive = dir.path().join("saves.tar.gz");
+  let mut tar = tar::Builder::new(GzEncoder::new(
+    File::create(&archive).unwrap(),
+    Compression::default(),
+  ));
+  tar.append_dir_all("AUTOSAVE2", &save_dir).unwrap();
+  tar.into_inner().unwrap().finish().unwrap();
+  let output_archive = dir.path().join("pc.zip");
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_archive.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let mut zip = ZipArchive::new(File::open(&output_archive).unwrap()).unwrap();
+  let mut data = vec![];
+  zip
+    .by_name("autosave2.dat")
+    .unwrap()
+    .read_to_end(&mut data)
+    .unwrap();
+  assert_eq!(&data[..4], b"GGDS");
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_archive.as_os_str(),
+  ]);
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("already exists"));
+}
+
+#[test]
+fn convert_reports_parallel_conversions_in_archive_order() {
+  let dir = tempfile::tempdir().unwrap();
+  let slots = ["MANUALSAVE0", "MANUALSAVE1", "MANUALSAVE2", "MANUALSAVE3"];
+  for slot in slots {
+    ps4_save(dir.path(), slot, false);
+  }
+  let archive = dir.path().join("saves.zip");
+  pack_zip(dir.path(), &slots, &archive);
+  let output_dir = dir.path().join("pc");
+  let report_file = dir.path().join("report.json");
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "-j".as_ref(),
+    "3".as_ref(),
+    "--report".as_ref(),
+    "json".as_ref(),
+    "--report-file".as_ref(),
+    report_file.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let reports: Vec<serde_json::Value> =
+    serde_json::from_slice(&fs::read(report_file).unwrap()).unwrap();
+  assert_eq!(reports.len(), slots.len());
+  for (report, slot) in reports.iter().zip(slots) {
+    assert!(report["save_file"].as_str().unwrap().contains(slot));
+    assert_eq!(report["status"], "converted");
+    // Each save only gets its own placeholder image warning.
+    assert_eq!(report["warnings"].as_array().unwrap().len(), 1);
+  }
+}
+
+#[test]
+fn convert_reports_a_single_save_archive_as_a_list() {
+  let dir = tempfile::tempdir().unwrap();
+  ps4_save(dir.path(), "AUTOSAVE0", true);
+  let archive = dir.path().join("saves.zip");
+  pack_zip(dir.path(), &["AUTOSAVE0"], &archive);
+  let output_dir = dir.path().join("pc");
+  let report_file = dir.path().join("report.json");
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--report".as_ref(),
+    "json".as_ref(),
+    "--report-file".as_ref(),
+    report_file.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let reports: Vec<serde_json::Value> =
+    serde_json::from_slice(&fs::read(report_file).unwrap()).unwrap();
+  assert_eq!(reports.len(), 1);
+  assert_eq!(reports[0]["status"], "converted");
+}
+
+#[test]
+fn convert_generates_the_same_save_from_an_archive_and_a_directory() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let archive = dir.path().join("saves.zip");
+  pack_zip(dir.path(), &["AUTOSAVE0"], &archive);
+
+  let archive_output = dir.path().join("from-archive");
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    archive_output.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  let dir_output = dir.path().join("from-dir");
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), dir_output.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  assert_eq!(
+    fs::read(archive_output.join("autosave0.dat")).unwrap(),
+    fs::read(dir_output.join("autosave0.dat")).unwrap()
+  );
+}
+
+#[test]
+fn convert_selects_the_saves_of_the_account() {
+  let dir = tempfile::tempdir().unwrap();
+  ps4_save(
+    &dir.path().join("PS4/SAVEDATA/1a2b3c4d5e6f7a8b/CUSA24767"),
+    "AUTOSAVE0",
+    true,
+  );
+  ps4_save(
+    &dir.path().join("PS4/SAVEDATA/8b7a6f5e4d3c2b1a/CUSA24767"),
+    "MANUALSAVE1",
+    true,
+  );
+  let archive = dir.path().join("saves.zip");
+  pack_zip(
+    dir.path(),
+    &[
+      "PS4/SAVEDATA/1a2b3c4d5e6f7a8b/CUSA24767/AUTOSAVE0",
+      "PS4/SAVEDATA/8b7a6f5e4d3c2b1a/CUSA24767/MANUALSAVE1",
+    ],
+    &archive,
+  );
+  let output_dir = dir.path().join("pc");
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--account".as_ref(),
+    "1A2B3C4D5E6F7A8B".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(output_dir.join("autosave0.dat").is_file());
+  assert!(!output_dir.join("manualsave1.dat").exists());
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--account".as_ref(),
+    "0000000000000000".as_ref(),
+  ]);
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("no save of account \"0000000000000000\""));
+}
+
+#[test]
+fn convert_logs_the_progress_without_a_terminal() {
+  let dir = tempfile::tempdir().unwrap();
+  ps4_save(dir.path(), "AUTOSAVE0", true);
+  ps4_save(dir.path(), "MANUALSAVE1", true);
+  let archive = dir.path().join("saves.zip");
+  pack_zip(dir.path(), &["AUTOSAVE0", "MANUALSAVE1"], &archive);
+  let output_dir = dir.path().join("pc");
+
+  let output = run([
+    archive.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--jobs".as_ref(),
+    "1".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let logs = stderr(&output);
+  assert!(logs.contains("Processed saves/AUTOSAVE0/checkpoint.dat (1 of 2)"));
+  assert!(logs.contains("Processed saves/MANUALSAVE1/checkpoint.dat (2 of 2)"));
+  assert!(logs.contains("Processed 2 of 2 saves in "));
+}
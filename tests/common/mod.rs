@@ -0,0 +1,203 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+
+use image::{ImageFormat, Rgb, RgbImage};
+
+pub static TITLE: &str = "The Point of the Spear - Level 12 - 05:06:07";
+pub static SUB_TITLE: &str = "Autosave";
+pub static CHECKSUM: u32 = 424242;
+
+// Layout of the GGDS header of the PC save files.
+pub static VERSION_OFFSET: usize = 0x04;
+pub static CHECKSUM_OFFSET: usize = 0x08;
+pub static DATA_LENGTH_OFFSET: usize = 0x0c;
+pub static IMAGE_LENGTH_OFFSET: usize = 0x10;
+pub static TITLE_OFFSET: usize = 0x14;
+pub static TITLE_CAPACITY: usize = 0x80;
+pub static SUB_TITLE_OFFSET: usize = TITLE_OFFSET + TITLE_CAPACITY;
+pub static SUB_TITLE_CAPACITY: usize = 0x100;
+pub static DATA_OFFSET: usize = SUB_TITLE_OFFSET + SUB_TITLE_CAPACITY;
+
+// Layout of the param.sfo files.
+static SFO_VERSION: u32 = 0x101;
+static SFO_HEADER_SIZE: usize = 0x14;
+static SFO_FORMAT_BINARY: u16 = 0x0004;
+static SFO_FORMAT_UTF8: u16 = 0x0204;
+static SFO_FORMAT_INT: u16 = 0x0404;
+
+enum Param<'a> {
+  Utf8(&'a str, usize),
+  Int(u32),
+  Binary(&'a [u8]),
+}
+
+/// Builds a `param.sfo` file resembling the ones written by the PS4 for the save data.
+/// It is written independently of the converter's SFO writer, so the tests don't share its mistakes.
+pub fn param_sfo(slot: &str) -> Vec<u8> {
+  let sub_title = format!("Horizon Forbidden West™ - {}", SUB_TITLE);
+  let params = [
+    ("ACCOUNT_ID", Param::Binary(&[1; 8])),
+    ("CATEGORY", Param::Utf8("sd", 4)),
+    ("MAINTITLE", Param::Utf8(TITLE, 128)),
+    ("SAVEDATA_DIRECTORY", Param::Utf8(slot, 32)),
+    ("SAVEDATA_LIST_PARAM", Param::Int(CHECKSUM)),
+    ("SUBTITLE", Param::Utf8(&sub_title, 128)),
+    ("TITLE_ID", Param::Utf8("CUSA24767", 16)),
+  ];
+
+  let mut entries = vec![];
+  let mut keys = vec![];
+  let mut data = vec![];
+  for (key, param) in &params {
+    let (format, mut bytes, capacity): (u16, Vec<u8>, usize) = match param {
+      Param::Utf8(value, capacity) => (
+        SFO_FORMAT_UTF8,
+        [value.as_bytes(), &[0]].concat(),
+        *capacity,
+      ),
+      Param::Int(value) => (SFO_FORMAT_INT, value.to_le_bytes().to_vec(), 4),
+      Param::Binary(value) => (SFO_FORMAT_BINARY, value.to_vec(), value.len()),
+    };
+
+    entries.extend_from_slice(&(keys.len() as u16).to_le_bytes());
+    entries.extend_from_slice(&format.to_le_bytes());
+    entries.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    entries.extend_from_slice(&(capacity as u32).to_le_bytes());
+    entries.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    keys.extend_from_slice(key.as_bytes());
+    keys.push(0);
+    bytes.resize(capacity, 0);
+    data.extend_from_slice(&bytes);
+  }
+  keys.resize(keys.len().next_multiple_of(4), 0);
+
+  let key_table_offset = SFO_HEADER_SIZE + entries.len();
+  let data_table_offset = key_table_offset + keys.len();
+  [
+    b"\0PSF".as_slice(),
+    &SFO_VERSION.to_le_bytes(),
+    &(key_table_offset as u32).to_le_bytes(),
+    &(data_table_offset as u32).to_le_bytes(),
+    &(params.len() as u32).to_le_bytes(),
+    &entries,
+    &keys,
+    &data,
+  ]
+  .concat()
+}
+
+pub fn read_u32(data: &[u8], offset: usize) -> u32 {
+  u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+/// Reads the null terminated string from the fixed size field.
+pub fn read_string(data: &[u8], offset: usize, capacity: usize) -> String {
+  let field = &data[offset..offset + capacity];
+  let end = field.iter().position(|&byte| byte == 0).unwrap();
+  String::from_utf8(field[..end].to_vec()).unwrap()
+}
+
+pub fn write_u32(data: &mut [u8], offset: usize, value: u32) {
+  data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Decrypted save data stand-in, the converter only requires it to not look encrypted.
+pub fn payload() -> Vec<u8> {
+  b"HFW checkpoint test data. ".repeat(1000)
+}
+
+/// Builds a PNG image with the size of the PS4 save icons.
+pub fn icon() -> Vec<u8> {
+  let image = RgbImage::from_fn(228, 128, |x, y| Rgb([x as u8, y as u8, 0x80]));
+  let mut output = Cursor::new(vec![]);
+  image.write_to(&mut output, ImageFormat::Png).unwrap();
+
+  output.into_inner()
+}
+
+/// Creates a decrypted PS4 save directory named after the slot.
+pub fn ps4_save(dir: &Path, slot: &str, with_icon: bool) -> PathBuf {
+  let save_dir = dir.join(slot);
+  let sce_sys = save_dir.join("sce_sys");
+  fs::create_dir_all(&sce_sys).unwrap();
+
+  fs::write(save_dir.join("checkpoint.dat"), payload()).unwrap();
+  fs::write(sce_sys.join("param.sfo"), param_sfo(slot)).unwrap();
+  if with_icon {
+    fs::write(sce_sys.join("icon0.png"), icon()).unwrap();
+  }
+
+  save_dir
+}
+
+fn command<I, S>(args: I) -> Command
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<std::ffi::OsStr>,
+{
+  let mut command = Command::new(env!("CARGO_BIN_EXE_hfw-save-converter"));
+  command
+    .arg("--no-logo")
+    .args(args)
+    .env("NO_COLOR", "1")
+    // Keeps the config file of the user running the tests out of them.
+    .env("XDG_CONFIG_HOME", env!("CARGO_TARGET_TMPDIR"))
+    .env_remove("RUST_LOG");
+
+  command
+}
+
+/// Runs the converter binary without the logo and colors.
+pub fn run<I, S>(args: I) -> Output
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<std::ffi::OsStr>,
+{
+  command(args).output().unwrap()
+}
+
+/// Runs the converter binary like [`run`], piping the input into its standard input.
+pub fn run_with_stdin<I, S>(args: I, input: &[u8]) -> Output
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<std::ffi::OsStr>,
+{
+  let mut child = command(args)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  child.stdin.take().unwrap().write_all(input).unwrap();
+
+  child.wait_with_output().unwrap()
+}
+
+pub fn stdout(output: &Output) -> String {
+  String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+  String::from_utf8_lossy(&output.stderr).into_owned()
+}
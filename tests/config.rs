@@ -0,0 +1,213 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+
+use common::{
+  icon, ps4_save, read_u32, run, stderr, stdout, DATA_LENGTH_OFFSET, DATA_OFFSET,
+  IMAGE_LENGTH_OFFSET,
+};
+
+mod common;
+
+#[test]
+fn config_provides_defaults_below_cli_flags() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+  let config = dir.path().join("config.toml");
+  fs::write(
+    &config,
+    format!(
+      "[convert]\noutput_dir = {:?}\nforce = true\n\n[log]\nlevel = \"warn\"\n",
+      output_dir
+    ),
+  )
+  .unwrap();
+  fs::create_dir_all(&output_dir).unwrap();
+  fs::write(output_dir.join("autosave0.dat"), b"existing").unwrap();
+
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    save_dir.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(fs::read(output_dir.join("autosave0.dat"))
+    .unwrap()
+    .starts_with(b"GGDS"));
+  assert!(!stderr(&output).contains("INFO"));
+
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    "-v".as_ref(),
+    "config".as_ref(),
+    "show".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  let effective = stdout(&output);
+  assert!(effective.contains("force = true"));
+  assert!(effective.contains("level = \"debug\""));
+}
+
+#[test]
+fn config_rejects_unknown_options() {
+  let dir = tempfile::tempdir().unwrap();
+  let config = dir.path().join("config.toml");
+  fs::write(&config, "[convert]\noverwrite = true\n").unwrap();
+
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    "config".as_ref(),
+    "show".as_ref(),
+  ]);
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("unknown field `overwrite`"));
+}
+
+#[test]
+fn cli_flags_turn_off_config_defaults() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+  let config = dir.path().join("config.toml");
+  fs::write(
+    &config,
+    format!("[convert]\noutput_dir = {:?}\nforce = true\n", output_dir),
+  )
+  .unwrap();
+  fs::create_dir_all(&output_dir).unwrap();
+  fs::write(output_dir.join("autosave0.dat"), b"existing").unwrap();
+
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    save_dir.as_os_str(),
+    "--no-force".as_ref(),
+  ]);
+  assert!(stderr(&output).contains("file already exists"));
+  assert_eq!(
+    fs::read(output_dir.join("autosave0.dat")).unwrap(),
+    b"existing"
+  );
+
+  // The last of the two flags wins.
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    save_dir.as_os_str(),
+    "--no-force".as_ref(),
+    "--force".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(fs::read(output_dir.join("autosave0.dat"))
+    .unwrap()
+    .starts_with(b"GGDS"));
+}
+
+#[test]
+fn config_keeps_backups_of_overwritten_saves() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+  let config = dir.path().join("config.toml");
+  fs::write(
+    &config,
+    format!(
+      "[convert]\noutput_dir = {:?}\nforce = true\nbackups = 2\n",
+      output_dir
+    ),
+  )
+  .unwrap();
+  fs::create_dir_all(&output_dir).unwrap();
+  fs::write(output_dir.join("autosave0.dat"), b"existing").unwrap();
+
+  for _ in 0..3 {
+    let output = run([
+      "--config".as_ref(),
+      config.as_os_str(),
+      save_dir.as_os_str(),
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+  }
+
+  let generated = fs::read(output_dir.join("autosave0.dat")).unwrap();
+  assert!(generated.starts_with(b"GGDS"));
+  assert_eq!(
+    fs::read(output_dir.join("autosave0.dat.1.bak")).unwrap(),
+    generated
+  );
+  assert_eq!(
+    fs::read(output_dir.join("autosave0.dat.2.bak")).unwrap(),
+    generated
+  );
+  assert!(!output_dir.join("autosave0.dat.3.bak").exists());
+
+  // Without overwriting there is nothing to back up.
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    save_dir.as_os_str(),
+    "--no-force".as_ref(),
+    "--backups".as_ref(),
+    "3".as_ref(),
+  ]);
+  assert!(stderr(&output).contains("file already exists"));
+  assert!(!output_dir.join("autosave0.dat.3.bak").exists());
+}
+
+#[test]
+fn config_raw_image_applies_to_edit() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), output_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  let save_path = output_dir.join("autosave0.dat");
+  let image_path = dir.path().join("image.png");
+  fs::write(&image_path, icon()).unwrap();
+  let config = dir.path().join("config.toml");
+  fs::write(&config, "[convert]\nraw_image = true\n").unwrap();
+
+  let image = |save_path: &std::path::Path| {
+    let data = fs::read(save_path).unwrap();
+    let start = DATA_OFFSET + read_u32(&data, DATA_LENGTH_OFFSET) as usize;
+    let length = read_u32(&data, IMAGE_LENGTH_OFFSET) as usize;
+    data[start..start + length].to_vec()
+  };
+  let edit = |extra: &[&str]| {
+    let mut args: Vec<&std::ffi::OsStr> = vec![
+      "--config".as_ref(),
+      config.as_os_str(),
+      "edit".as_ref(),
+      save_path.as_os_str(),
+      "--image".as_ref(),
+      image_path.as_os_str(),
+    ];
+    args.extend(extra.iter().map(std::ffi::OsStr::new));
+    let output = run(args);
+    assert!(output.status.success(), "{}", stderr(&output));
+  };
+
+  edit(&[]);
+  assert_eq!(image(&save_path), icon());
+
+  edit(&["--no-raw-image"]);
+  assert_ne!(image(&save_path), icon());
+}
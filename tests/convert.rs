@@ -0,0 +1,271 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::path::Path;
+
+use common::{
+  ps4_save, read_string, read_u32, run, run_with_stdin, stderr, stdout, CHECKSUM, CHECKSUM_OFFSET,
+  DATA_LENGTH_OFFSET, DATA_OFFSET, IMAGE_LENGTH_OFFSET, SUB_TITLE, SUB_TITLE_CAPACITY,
+  SUB_TITLE_OFFSET, TITLE, TITLE_CAPACITY, TITLE_OFFSET, VERSION_OFFSET,
+};
+
+mod common;
+
+#[test]
+fn convert_writes_the_ggds_layout() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), output_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let data = fs::read(output_dir.join("autosave0.dat")).unwrap();
+  let payload = common::payload();
+  let data_length = read_u32(&data, DATA_LENGTH_OFFSET) as usize;
+  let image_length = read_u32(&data, IMAGE_LENGTH_OFFSET) as usize;
+
+  assert_eq!(&data[..4], b"GGDS");
+  assert_eq!(read_u32(&data, VERSION_OFFSET), 1);
+  assert_eq!(read_u32(&data, CHECKSUM_OFFSET), CHECKSUM);
+  assert_eq!(data_length, payload.len());
+  assert_eq!(read_string(&data, TITLE_OFFSET, TITLE_CAPACITY), TITLE);
+  assert_eq!(
+    read_string(&data, SUB_TITLE_OFFSET, SUB_TITLE_CAPACITY),
+    SUB_TITLE
+  );
+  assert_eq!(
+    &data[DATA_OFFSET..DATA_OFFSET + data_length],
+    payload.as_slice()
+  );
+  assert_eq!(data.len(), DATA_OFFSET + data_length + image_length);
+  assert!(data[DATA_OFFSET + data_length..].starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn convert_skips_existing_saves_without_force() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE1", false);
+  let output_dir = dir.path().join("pc");
+  let args = [save_dir.as_os_str(), "-o".as_ref(), output_dir.as_os_str()];
+
+  assert!(run(args).status.success());
+  fs::write(output_dir.join("autosave1.dat"), b"existing").unwrap();
+
+  let output = run(args);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    fs::read(output_dir.join("autosave1.dat")).unwrap(),
+    b"existing"
+  );
+}
+
+#[test]
+fn convert_pipes_the_save_through_stdio() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE2", true);
+  let sce_sys = save_dir.join("sce_sys");
+
+  let output = run_with_stdin(
+    [
+      "-".as_ref(),
+      "-o".as_ref(),
+      "-".as_ref(),
+      "--sce-sys".as_ref(),
+      sce_sys.as_os_str(),
+    ],
+    &common::payload(),
+  );
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let data = &output.stdout;
+  assert_eq!(&data[..4], b"GGDS");
+  assert_eq!(read_u32(data, CHECKSUM_OFFSET), CHECKSUM);
+  assert_eq!(read_string(data, TITLE_OFFSET, TITLE_CAPACITY), TITLE);
+  assert!(stderr(&output).contains("standard output"));
+}
+
+#[test]
+fn convert_command_matches_the_bare_path_alias() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE3", true);
+  let alias_dir = dir.path().join("alias");
+  let command_dir = dir.path().join("command");
+
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), alias_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  let output = run([
+    "convert".as_ref(),
+    save_dir.as_os_str(),
+    "-o".as_ref(),
+    command_dir.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  assert_eq!(
+    fs::read(alias_dir.join("autosave3.dat")).unwrap(),
+    fs::read(command_dir.join("autosave3.dat")).unwrap()
+  );
+}
+
+/// Replaces the bytes of the `param.sfo` file param value with another one of the same length.
+fn patch_param_sfo(save_dir: &Path, from: &[u8], to: &[u8]) {
+  let sfo_path = save_dir.join("sce_sys").join("param.sfo");
+  let mut sfo = fs::read(&sfo_path).unwrap();
+  let position = sfo
+    .windows(from.len())
+    .position(|value| value == from)
+    .unwrap();
+  sfo[position..position + to.len()].copy_from_slice(to);
+  fs::write(&sfo_path, sfo).unwrap();
+}
+
+#[test]
+fn convert_refuses_param_sfo_of_other_games() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE4", true);
+  patch_param_sfo(&save_dir, b"CUSA24767", b"CUSA00001");
+  patch_param_sfo(
+    &save_dir,
+    b"Horizon Forbidden West",
+    b"Another Game Entirely!",
+  );
+
+  let output = run([
+    save_dir.as_os_str(),
+    "-o".as_ref(),
+    dir.path().join("pc").as_os_str(),
+  ]);
+  assert!(!output.status.success());
+  assert!(
+    stderr(&output)
+      .contains("title ID \"CUSA00001\" is not a known Horizon Forbidden West release"),
+    "{}",
+    stderr(&output)
+  );
+}
+
+#[test]
+fn convert_accepts_param_sfo_of_other_releases() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE4", true);
+  // A title ID missing from the known releases, as the ones of other regions are.
+  patch_param_sfo(&save_dir, b"CUSA24767", b"CUSA24892");
+  let output_dir = dir.path().join("pc");
+
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), output_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stderr(&output).contains("\"CUSA24892\" is not a known Horizon Forbidden West release"));
+  assert!(output_dir.join("autosave4.dat").is_file());
+}
+
+#[test]
+fn convert_options_are_refused_with_other_commands() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+
+  let output = run([
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--force".as_ref(),
+    "inspect".as_ref(),
+    save_dir.as_os_str(),
+  ]);
+  assert_eq!(output.status.code(), Some(2));
+  assert!(
+    stderr(&output).contains("'--output' can only be used with the convert command"),
+    "{}",
+    stderr(&output)
+  );
+  assert!(!output_dir.exists());
+
+  // The global options are still accepted before any command.
+  let output = run(["-v".as_ref(), "inspect".as_ref(), save_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn convert_reports_missing_paths() {
+  let dir = tempfile::tempdir().unwrap();
+  let missing = dir.path().join("AUTOSAVE0");
+
+  let output = run([missing.as_os_str(), "--report".as_ref(), "json".as_ref()]);
+  assert!(!output.status.success());
+  let report: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+  assert_eq!(report["status"], "failed");
+  assert_eq!(report["error"]["kind"], "wrong_folder");
+}
+
+#[test]
+fn convert_logs_single_modules_with_log_filter() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+
+  let output = run([
+    "--log-filter".as_ref(),
+    "sfo=trace,log=debug".as_ref(),
+    save_dir.as_os_str(),
+    "-o".as_ref(),
+    dir.path().join("pc").as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  let logs = stderr(&output);
+  assert!(logs.contains("Parsed param.sfo header"), "{}", logs);
+  assert!(
+    logs.contains(&format!("MAINTITLE: {:?}", TITLE)),
+    "{}",
+    logs
+  );
+  assert!(logs.contains("Timing summary"), "{}", logs);
+  // The other modules stay at the default level.
+  assert!(!logs.contains("Resolving save file location"), "{}", logs);
+}
+
+#[test]
+fn convert_finds_the_title_in_the_save_data_without_param_sfo() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "MANUALSAVE2", true);
+  fs::remove_file(save_dir.join("sce_sys/param.sfo")).unwrap();
+  let mut payload = fs::read(save_dir.join("checkpoint.dat")).unwrap();
+  payload.extend_from_slice(b"\0\x01");
+  payload.extend_from_slice(TITLE.as_bytes());
+  payload.extend_from_slice(b"\0\x02");
+  fs::write(save_dir.join("checkpoint.dat"), &payload).unwrap();
+  let output_dir = dir.path().join("pc");
+
+  let output = run([
+    save_dir.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--report".as_ref(),
+    "json".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let data = fs::read(output_dir.join("manualsave2.dat")).unwrap();
+  assert_eq!(read_string(&data, TITLE_OFFSET, TITLE_CAPACITY), TITLE);
+  let report: serde_json::Value = serde_json::from_str(&stdout(&output)).unwrap();
+  assert_eq!(report["metadata"]["title"]["value"], TITLE);
+  assert_eq!(report["metadata"]["title"]["source"], "payload");
+  assert_eq!(report["metadata"]["file_name"]["source"], "save_dir");
+
+  let output = run(["inspect".as_ref(), save_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stdout(&output).contains(&format!("Title:       {:?}", TITLE)));
+}
@@ -0,0 +1,140 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use common::{
+  ps4_save, read_u32, run, stderr, write_u32, DATA_LENGTH_OFFSET, DATA_OFFSET, IMAGE_LENGTH_OFFSET,
+  SUB_TITLE_OFFSET, TITLE_OFFSET, VERSION_OFFSET,
+};
+
+mod common;
+
+/// Converts a PS4 save and returns the path to the generated PC save file.
+fn pc_save(dir: &Path) -> PathBuf {
+  let save_dir = ps4_save(dir, "AUTOSAVE0", true);
+  let output_dir = dir.join("pc");
+
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), output_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  output_dir.join("autosave0.dat")
+}
+
+#[test]
+fn edit_keeps_unknown_bytes() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_path = pc_save(dir.path());
+
+  let mut data = fs::read(&save_path).unwrap();
+  data[SUB_TITLE_OFFSET - 4..SUB_TITLE_OFFSET].copy_from_slice(b"JUNK");
+  data.extend_from_slice(b"TRAILING");
+  fs::write(&save_path, &data).unwrap();
+
+  let output = run([
+    "edit".as_ref(),
+    save_path.as_os_str(),
+    "--sub-title".as_ref(),
+    "Manual Save".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let edited = fs::read(&save_path).unwrap();
+  assert_eq!(
+    &edited[TITLE_OFFSET..SUB_TITLE_OFFSET],
+    &data[TITLE_OFFSET..SUB_TITLE_OFFSET]
+  );
+  assert_eq!(
+    &edited[SUB_TITLE_OFFSET..SUB_TITLE_OFFSET + 12],
+    b"Manual Save\0"
+  );
+  assert!(edited.ends_with(b"TRAILING"));
+  assert_eq!(edited.len(), data.len());
+}
+
+#[test]
+fn edit_refuses_unknown_versions_unless_allowed() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_path = pc_save(dir.path());
+
+  let mut data = fs::read(&save_path).unwrap();
+  write_u32(&mut data, VERSION_OFFSET, 2);
+  fs::write(&save_path, &data).unwrap();
+
+  let args = [
+    "edit".as_ref(),
+    save_path.as_os_str(),
+    "--sub-title".as_ref(),
+    "Manual Save".as_ref(),
+  ];
+  let output = run(args);
+  assert!(!output.status.success());
+  assert!(stderr(&output).contains("refusing to write save file version 2"));
+  assert_eq!(fs::read(&save_path).unwrap(), data);
+
+  let output = run(args.into_iter().chain(["--allow-unknown-version".as_ref()]));
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(read_u32(&fs::read(&save_path).unwrap(), VERSION_OFFSET), 2);
+}
+
+#[test]
+fn edit_strips_the_image_to_the_placeholder() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_path = pc_save(dir.path());
+  let data = fs::read(&save_path).unwrap();
+
+  let output = run([
+    "edit".as_ref(),
+    save_path.as_os_str(),
+    "--strip-image".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let edited = fs::read(&save_path).unwrap();
+  let data_len = read_u32(&edited, DATA_LENGTH_OFFSET) as usize;
+  let image_len = read_u32(&edited, IMAGE_LENGTH_OFFSET) as usize;
+  let image = &edited[DATA_OFFSET + data_len..DATA_OFFSET + data_len + image_len];
+  assert!(image.starts_with(b"\x89PNG"));
+  assert_ne!(image, &data[DATA_OFFSET + data_len..]);
+}
+
+#[cfg(unix)]
+#[test]
+fn edit_keeps_the_file_permissions() {
+  use std::os::unix::fs::PermissionsExt;
+
+  let dir = tempfile::tempdir().unwrap();
+  let save_path = pc_save(dir.path());
+  assert_ne!(
+    fs::metadata(&save_path).unwrap().permissions().mode() & 0o777,
+    0o600
+  );
+  fs::set_permissions(&save_path, fs::Permissions::from_mode(0o640)).unwrap();
+
+  let output = run([
+    "edit".as_ref(),
+    save_path.as_os_str(),
+    "--sub-title".as_ref(),
+    "Manual Save".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    fs::metadata(&save_path).unwrap().permissions().mode() & 0o777,
+    0o640
+  );
+}
@@ -0,0 +1,115 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+
+use common::{ps4_save, run, stderr, stdout};
+
+mod common;
+
+fn outcome<'a>(stdout: &'a str, field: &str) -> &'a str {
+  stdout
+    .lines()
+    .map(str::trim)
+    .find_map(|line| {
+      line
+        .strip_prefix(field)
+        .filter(|rest| rest.starts_with(' '))
+    })
+    .unwrap_or_else(|| panic!("no outcome for {} in:\n{}", field, stdout))
+    .trim()
+}
+
+#[test]
+fn roundtrip_preserves_payload_icon_and_sfo_fields() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE0", true);
+
+  let output = run([
+    "roundtrip".as_ref(),
+    save_dir.as_os_str(),
+    "--raw-image".as_ref(),
+  ]);
+  let stdout = stdout(&output);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  for field in [
+    "checkpoint.dat",
+    "icon0.png",
+    "CATEGORY",
+    "MAINTITLE",
+    "SUBTITLE",
+    "SAVEDATA_DIRECTORY",
+    "SAVEDATA_LIST_PARAM",
+  ] {
+    assert_eq!(outcome(&stdout, field), "identical", "{}", field);
+  }
+}
+
+#[test]
+fn roundtrip_reports_the_normalized_icon() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "AUTOSAVE1", true);
+
+  let output = run(["roundtrip".as_ref(), save_dir.as_os_str()]);
+  let stdout = stdout(&output);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  assert!(
+    outcome(&stdout, "icon0.png")
+      .starts_with("not preserved, normalized to the PC thumbnail format"),
+    "{}",
+    stdout
+  );
+  assert_eq!(outcome(&stdout, "checkpoint.dat"), "identical");
+}
+
+#[test]
+fn roundtrip_reports_params_that_cannot_be_preserved() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "MANUALSAVE1", false);
+
+  let output = run(["roundtrip".as_ref(), save_dir.as_os_str()]);
+  let stdout = stdout(&output);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  assert!(outcome(&stdout, "ACCOUNT_ID").starts_with("not preserved"));
+  assert!(outcome(&stdout, "TITLE_ID").starts_with("not preserved"));
+  assert!(outcome(&stdout, "icon0.png").starts_with("not preserved"));
+  assert_eq!(outcome(&stdout, "checkpoint.dat"), "identical");
+}
+
+#[test]
+fn roundtrip_keeps_the_intermediate_saves() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(dir.path(), "QUICKSAVE2", true);
+  let keep = dir.path().join("keep");
+
+  let output = run([
+    "roundtrip".as_ref(),
+    save_dir.as_os_str(),
+    "--keep".as_ref(),
+    keep.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  assert!(keep.join("pc").join("quicksave2.dat").is_file());
+  assert_eq!(
+    fs::read(keep.join("ps4").join("QUICKSAVE2").join("checkpoint.dat")).unwrap(),
+    common::payload()
+  );
+}
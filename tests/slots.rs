@@ -0,0 +1,102 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+
+use common::{ps4_save, run, stderr, stdout, SUB_TITLE, TITLE, TITLE_OFFSET};
+
+mod common;
+
+#[test]
+fn slots_lists_extracted_and_converted_saves() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(&dir.path().join("ps4"), "AUTOSAVE0", true);
+  let pc_dir = dir.path().join("pc");
+
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), pc_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let extracted_dir = dir.path().join("extracted");
+  let output = run([
+    "extract".as_ref(),
+    pc_dir.join("autosave0.dat").as_os_str(),
+    "-o".as_ref(),
+    extracted_dir.as_os_str(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert_eq!(
+    fs::read(extracted_dir.join("AUTOSAVE0").join("checkpoint.dat")).unwrap(),
+    common::payload()
+  );
+
+  for (path, format) in [(&pc_dir, "PC"), (&extracted_dir, "PS4")] {
+    let output = run(["slots".as_ref(), path.as_os_str()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let slots = stdout(&output);
+    let slot = slots.lines().next().unwrap();
+    assert!(slot.starts_with("AUTOSAVE0"), "{}", slots);
+    assert!(slot.contains(format), "{}", slots);
+    assert!(
+      slot.contains(TITLE) && slot.contains(SUB_TITLE),
+      "{}",
+      slots
+    );
+  }
+}
+
+#[test]
+fn slots_sorts_saves_by_playtime() {
+  let dir = tempfile::tempdir().unwrap();
+  let pc_dir = dir.path().join("pc");
+
+  for (slot, playtime) in [
+    ("AUTOSAVE0", None),
+    ("AUTOSAVE1", Some("10:00:00")),
+    ("AUTOSAVE2", None),
+  ] {
+    let save_dir = ps4_save(&dir.path().join("ps4"), slot, true);
+    let mut args = vec![
+      save_dir.into_os_string(),
+      "-o".into(),
+      pc_dir.clone().into(),
+    ];
+    if let Some(playtime) = playtime {
+      args.extend(["--playtime".into(), playtime.into()]);
+    }
+
+    let output = run(args);
+    assert!(output.status.success(), "{}", stderr(&output));
+  }
+
+  // Titles that can't be parsed are listed after all the others.
+  let save_path = pc_dir.join("autosave0.dat");
+  let mut data = fs::read(&save_path).unwrap();
+  data[TITLE_OFFSET..TITLE_OFFSET + 8].copy_from_slice(b"Unknown\0");
+  fs::write(&save_path, &data).unwrap();
+
+  let output = run(["slots".as_ref(), pc_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let slots = stdout(&output)
+    .lines()
+    .filter_map(|line| line.split_whitespace().next())
+    .filter(|slot| slot.starts_with("AUTOSAVE"))
+    .map(str::to_owned)
+    .collect::<Vec<_>>();
+  assert_eq!(slots, ["AUTOSAVE1", "AUTOSAVE2", "AUTOSAVE0"]);
+}
@@ -0,0 +1,55 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+
+use common::{ps4_save, read_u32, run, stderr, write_u32, CHECKSUM, CHECKSUM_OFFSET};
+
+mod common;
+
+#[test]
+fn verify_restores_missing_checksum() {
+  let dir = tempfile::tempdir().unwrap();
+  let save_dir = ps4_save(&dir.path().join("ps4"), "AUTOSAVE0", true);
+  let pc_dir = dir.path().join("pc");
+
+  let output = run([save_dir.as_os_str(), "-o".as_ref(), pc_dir.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let save_path = pc_dir.join("autosave0.dat");
+  let mut data = fs::read(&save_path).unwrap();
+  write_u32(&mut data, CHECKSUM_OFFSET, 0);
+  fs::write(&save_path, &data).unwrap();
+
+  let output = run(["verify".as_ref(), save_path.as_os_str()]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stderr(&output).contains("Save file has no checksum"));
+
+  let output = run([
+    "verify".as_ref(),
+    save_path.as_os_str(),
+    "--source".as_ref(),
+    save_dir.as_os_str(),
+    "--fix-checksum".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+
+  let data = fs::read(&save_path).unwrap();
+  assert_eq!(read_u32(&data, CHECKSUM_OFFSET), CHECKSUM);
+  // The temporary file is renamed over the save file.
+  assert_eq!(fs::read_dir(&pc_dir).unwrap().count(), 1);
+}
@@ -0,0 +1,75 @@
+/*
+ * hfw-save-converter
+ * Copyright (c) 2024 Krzysztof Saczuk <zakku@zakku.eu>.
+ *
+ * This program is free software: you can redistribute it and/or modify it under
+ * the terms of the GNU General Public License as published by the Free Software
+ * Foundation, either version 3 of the License, or (at your option) any later
+ * version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY
+ * WARRANTY; without even the implied warranty of  MERCHANTABILITY or FITNESS FOR
+ * A PARTICULAR PURPOSE. See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License along with
+ * this program.  If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use std::fs;
+
+use common::{ps4_save, run, stderr};
+
+mod common;
+
+#[test]
+fn watch_converts_each_payload_once() {
+  let dir = tempfile::tempdir().unwrap();
+  let drop_dir = dir.path().join("drop");
+  ps4_save(&drop_dir.join("usb"), "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+  let args = [
+    "watch".as_ref(),
+    drop_dir.as_os_str(),
+    "-o".as_ref(),
+    output_dir.as_os_str(),
+    "--once".as_ref(),
+    "--interval".as_ref(),
+    "1".as_ref(),
+  ];
+
+  let output = run(args);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(output_dir.join("autosave0.dat").is_file());
+
+  // The same save data copied again under another slot is already converted.
+  ps4_save(&drop_dir, "MANUALSAVE1", true);
+  let output = run(args);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(stderr(&output).contains("already converted"));
+  assert_eq!(fs::read_dir(&output_dir).unwrap().count(), 1);
+}
+
+#[test]
+fn watch_takes_the_output_dir_from_the_config() {
+  let dir = tempfile::tempdir().unwrap();
+  let drop_dir = dir.path().join("drop");
+  ps4_save(&drop_dir, "AUTOSAVE0", true);
+  let output_dir = dir.path().join("pc");
+  let config = dir.path().join("config.toml");
+  fs::write(
+    &config,
+    format!("[convert]\noutput_dir = {:?}\n", output_dir),
+  )
+  .unwrap();
+
+  let output = run([
+    "--config".as_ref(),
+    config.as_os_str(),
+    "watch".as_ref(),
+    drop_dir.as_os_str(),
+    "--once".as_ref(),
+    "--no-raw-image".as_ref(),
+  ]);
+  assert!(output.status.success(), "{}", stderr(&output));
+  assert!(output_dir.join("autosave0.dat").is_file());
+}